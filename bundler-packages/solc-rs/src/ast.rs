@@ -0,0 +1,650 @@
+use crate::error::SolcError;
+use crate::models::{SolcOutput, SolcSourceEntry};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A parsed `src` attribute, `start:length:sourceIndex`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Src {
+    /// Byte offset, `None` for the `-1` solc uses when the node has no range
+    pub start: Option<usize>,
+    /// Byte length, `None` for the `-1` solc uses when the node has no range
+    pub length: Option<usize>,
+    /// Source id, `-1` if the node has no source
+    pub index: i64,
+}
+
+impl<'de> Deserialize<'de> for Src {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let src = String::deserialize(deserializer)?;
+        let mut parts = src.split(':');
+        let mut next = || {
+            parts
+                .next()
+                .and_then(|part| part.parse::<i64>().ok())
+                .ok_or_else(|| de::Error::custom(format!("invalid src attribute: {}", src)))
+        };
+        Ok(Src {
+            start: usize::try_from(next()?).ok(),
+            length: usize::try_from(next()?).ok(),
+            index: next()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContractKind {
+    Contract,
+    Interface,
+    Library,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FunctionKind {
+    Function,
+    Constructor,
+    Receive,
+    Fallback,
+    FreeFunction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Visibility {
+    External,
+    Public,
+    Internal,
+    Private,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StateMutability {
+    Pure,
+    View,
+    Nonpayable,
+    Payable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Mutability {
+    Mutable,
+    Immutable,
+    Constant,
+}
+
+/// NatSpec attached to a declaration
+///
+/// solc >= 0.6.3 emits a `StructuredDocumentation` node, older compilers a plain string.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Documentation {
+    Structured(StructuredDocumentation),
+    Text(String),
+}
+
+impl Documentation {
+    pub fn text(&self) -> &str {
+        match self {
+            Documentation::Structured(documentation) => &documentation.text,
+            Documentation::Text(text) => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StructuredDocumentation {
+    pub id: i64,
+    pub src: Src,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeDescriptions {
+    pub type_identifier: Option<String>,
+    pub type_string: Option<String>,
+}
+
+/// Root of the compact AST of one source file
+///
+/// Declarations are typed; statements, expressions and type names are kept as JSON so the
+/// model stays small.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceUnit {
+    pub id: i64,
+    pub src: Src,
+    pub absolute_path: String,
+    #[serde(default)]
+    pub exported_symbols: BTreeMap<String, Vec<i64>>,
+    pub license: Option<String>,
+    pub nodes: Vec<Node>,
+}
+
+impl SourceUnit {
+    /// Every contract, interface and library declared in this file
+    pub fn contracts(&self) -> impl Iterator<Item = &ContractDefinition> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::ContractDefinition(contract) => Some(contract),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PragmaDirective {
+    pub id: i64,
+    pub src: Src,
+    /// Tokens of the pragma, e.g. `["solidity", "^", "0.8", ".20"]`
+    pub literals: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDirective {
+    pub id: i64,
+    pub src: Src,
+    /// The import path as written
+    pub file: String,
+    pub absolute_path: String,
+    pub source_unit: i64,
+    #[serde(default)]
+    pub unit_alias: String,
+    #[serde(default)]
+    pub symbol_aliases: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InheritanceSpecifier {
+    pub id: i64,
+    pub src: Src,
+    pub base_name: BaseName,
+}
+
+/// The `IdentifierPath` (or `UserDefinedTypeName` before 0.8) naming a base contract
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseName {
+    pub name: Option<String>,
+    pub referenced_declaration: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub contract_kind: ContractKind,
+    #[serde(rename = "abstract", default)]
+    pub is_abstract: bool,
+    #[serde(default)]
+    pub base_contracts: Vec<InheritanceSpecifier>,
+    #[serde(default)]
+    pub linearized_base_contracts: Vec<i64>,
+    pub documentation: Option<Documentation>,
+    pub nodes: Vec<Node>,
+}
+
+impl ContractDefinition {
+    /// Functions, constructors, `receive` and `fallback` declared in this contract
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionDefinition> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::FunctionDefinition(function) => Some(function),
+            _ => None,
+        })
+    }
+
+    /// State variables declared in this contract
+    pub fn state_variables(&self) -> impl Iterator<Item = &VariableDeclaration> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::VariableDeclaration(variable) => Some(variable),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub kind: FunctionKind,
+    pub visibility: Visibility,
+    pub state_mutability: StateMutability,
+    #[serde(rename = "virtual", default)]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub implemented: bool,
+    /// Four byte selector, only set for public and external functions
+    pub function_selector: Option<String>,
+    pub documentation: Option<Documentation>,
+    pub parameters: ParameterList,
+    pub return_parameters: ParameterList,
+    #[serde(default)]
+    pub modifiers: Vec<serde_json::Value>,
+    pub body: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifierDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub visibility: Visibility,
+    #[serde(rename = "virtual", default)]
+    pub is_virtual: bool,
+    pub documentation: Option<Documentation>,
+    pub parameters: ParameterList,
+    pub body: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterList {
+    pub id: i64,
+    pub src: Src,
+    pub parameters: Vec<VariableDeclaration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableDeclaration {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub state_variable: bool,
+    #[serde(default)]
+    pub constant: bool,
+    /// Only emitted by solc >= 0.6.5
+    pub mutability: Option<Mutability>,
+    #[serde(default)]
+    pub indexed: bool,
+    /// Getter selector of public state variables
+    pub function_selector: Option<String>,
+    pub documentation: Option<Documentation>,
+    #[serde(default)]
+    pub type_descriptions: TypeDescriptions,
+    pub type_name: Option<serde_json::Value>,
+    pub value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    #[serde(default)]
+    pub anonymous: bool,
+    pub event_selector: Option<String>,
+    pub documentation: Option<Documentation>,
+    pub parameters: ParameterList,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub error_selector: Option<String>,
+    pub documentation: Option<Documentation>,
+    pub parameters: ParameterList,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub canonical_name: Option<String>,
+    pub documentation: Option<Documentation>,
+    pub members: Vec<VariableDeclaration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnumDefinition {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+    pub canonical_name: Option<String>,
+    pub documentation: Option<Documentation>,
+    pub members: Vec<EnumValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EnumValue {
+    pub id: i64,
+    pub src: Src,
+    pub name: String,
+}
+
+/// A top-level or contract-level node, dispatched on `nodeType`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    PragmaDirective(PragmaDirective),
+    ImportDirective(ImportDirective),
+    ContractDefinition(ContractDefinition),
+    FunctionDefinition(FunctionDefinition),
+    ModifierDefinition(ModifierDefinition),
+    VariableDeclaration(VariableDeclaration),
+    EventDefinition(EventDefinition),
+    ErrorDefinition(ErrorDefinition),
+    StructDefinition(StructDefinition),
+    EnumDefinition(EnumDefinition),
+    /// `UsingForDirective`, `UserDefinedValueTypeDefinition` and anything newer
+    Other(serde_json::Value),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let node_type = value.get("nodeType").and_then(serde_json::Value::as_str);
+        fn typed<T: de::DeserializeOwned, E: de::Error>(value: serde_json::Value) -> Result<T, E> {
+            serde_json::from_value(value).map_err(E::custom)
+        }
+        Ok(match node_type {
+            Some("PragmaDirective") => Node::PragmaDirective(typed(value)?),
+            Some("ImportDirective") => Node::ImportDirective(typed(value)?),
+            Some("ContractDefinition") => Node::ContractDefinition(typed(value)?),
+            Some("FunctionDefinition") => Node::FunctionDefinition(typed(value)?),
+            Some("ModifierDefinition") => Node::ModifierDefinition(typed(value)?),
+            Some("VariableDeclaration") => Node::VariableDeclaration(typed(value)?),
+            Some("EventDefinition") => Node::EventDefinition(typed(value)?),
+            Some("ErrorDefinition") => Node::ErrorDefinition(typed(value)?),
+            Some("StructDefinition") => Node::StructDefinition(typed(value)?),
+            Some("EnumDefinition") => Node::EnumDefinition(typed(value)?),
+            _ => Node::Other(value),
+        })
+    }
+}
+
+/// Walks a [`SourceUnit`]; override the methods for the nodes you care about
+///
+/// Every method defaults to visiting the node's children through the matching `walk_*`
+/// function, so an override should call it to keep descending.
+pub trait Visitor {
+    fn visit_source_unit(&mut self, source_unit: &SourceUnit) {
+        walk_source_unit(self, source_unit);
+    }
+
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node);
+    }
+
+    fn visit_pragma(&mut self, _pragma: &PragmaDirective) {}
+
+    fn visit_import(&mut self, _import: &ImportDirective) {}
+
+    fn visit_contract(&mut self, contract: &ContractDefinition) {
+        walk_contract(self, contract);
+    }
+
+    fn visit_function(&mut self, function: &FunctionDefinition) {
+        walk_function(self, function);
+    }
+
+    fn visit_modifier(&mut self, modifier: &ModifierDefinition) {
+        walk_modifier(self, modifier);
+    }
+
+    fn visit_variable(&mut self, variable: &VariableDeclaration) {
+        walk_variable(self, variable);
+    }
+
+    fn visit_event(&mut self, event: &EventDefinition) {
+        walk_event(self, event);
+    }
+
+    fn visit_error(&mut self, error: &ErrorDefinition) {
+        walk_error(self, error);
+    }
+
+    fn visit_struct(&mut self, definition: &StructDefinition) {
+        walk_struct(self, definition);
+    }
+
+    fn visit_enum(&mut self, definition: &EnumDefinition) {
+        walk_documentation(self, definition.documentation.as_ref());
+    }
+
+    /// NatSpec of any declaration that has it
+    fn visit_documentation(&mut self, _documentation: &Documentation) {}
+
+    fn visit_other(&mut self, _node: &serde_json::Value) {}
+}
+
+pub fn walk_source_unit<V: Visitor + ?Sized>(visitor: &mut V, source_unit: &SourceUnit) {
+    for node in &source_unit.nodes {
+        visitor.visit_node(node);
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &Node) {
+    match node {
+        Node::PragmaDirective(pragma) => visitor.visit_pragma(pragma),
+        Node::ImportDirective(import) => visitor.visit_import(import),
+        Node::ContractDefinition(contract) => visitor.visit_contract(contract),
+        Node::FunctionDefinition(function) => visitor.visit_function(function),
+        Node::ModifierDefinition(modifier) => visitor.visit_modifier(modifier),
+        Node::VariableDeclaration(variable) => visitor.visit_variable(variable),
+        Node::EventDefinition(event) => visitor.visit_event(event),
+        Node::ErrorDefinition(error) => visitor.visit_error(error),
+        Node::StructDefinition(definition) => visitor.visit_struct(definition),
+        Node::EnumDefinition(definition) => visitor.visit_enum(definition),
+        Node::Other(node) => visitor.visit_other(node),
+    }
+}
+
+pub fn walk_contract<V: Visitor + ?Sized>(visitor: &mut V, contract: &ContractDefinition) {
+    walk_documentation(visitor, contract.documentation.as_ref());
+    for node in &contract.nodes {
+        visitor.visit_node(node);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDefinition) {
+    walk_documentation(visitor, function.documentation.as_ref());
+    walk_parameters(visitor, &function.parameters);
+    walk_parameters(visitor, &function.return_parameters);
+}
+
+pub fn walk_modifier<V: Visitor + ?Sized>(visitor: &mut V, modifier: &ModifierDefinition) {
+    walk_documentation(visitor, modifier.documentation.as_ref());
+    walk_parameters(visitor, &modifier.parameters);
+}
+
+pub fn walk_variable<V: Visitor + ?Sized>(visitor: &mut V, variable: &VariableDeclaration) {
+    walk_documentation(visitor, variable.documentation.as_ref());
+}
+
+pub fn walk_event<V: Visitor + ?Sized>(visitor: &mut V, event: &EventDefinition) {
+    walk_documentation(visitor, event.documentation.as_ref());
+    walk_parameters(visitor, &event.parameters);
+}
+
+pub fn walk_error<V: Visitor + ?Sized>(visitor: &mut V, error: &ErrorDefinition) {
+    walk_documentation(visitor, error.documentation.as_ref());
+    walk_parameters(visitor, &error.parameters);
+}
+
+pub fn walk_struct<V: Visitor + ?Sized>(visitor: &mut V, definition: &StructDefinition) {
+    walk_documentation(visitor, definition.documentation.as_ref());
+    for member in &definition.members {
+        visitor.visit_variable(member);
+    }
+}
+
+fn walk_parameters<V: Visitor + ?Sized>(visitor: &mut V, parameters: &ParameterList) {
+    for parameter in &parameters.parameters {
+        visitor.visit_variable(parameter);
+    }
+}
+
+fn walk_documentation<V: Visitor + ?Sized>(visitor: &mut V, documentation: Option<&Documentation>) {
+    if let Some(documentation) = documentation {
+        visitor.visit_documentation(documentation);
+    }
+}
+
+impl SolcSourceEntry {
+    /// Parse `ast` into a [`SourceUnit`], which requires the `ast` output to be selected
+    pub fn source_unit(&self) -> Result<Option<SourceUnit>, SolcError> {
+        self.ast
+            .as_ref()
+            .map(|ast| {
+                SourceUnit::deserialize(ast)
+                    .map_err(|e| SolcError::SerializationError(format!("Invalid AST: {}", e)))
+            })
+            .transpose()
+    }
+}
+
+impl SolcOutput {
+    /// Typed ASTs of every source that has one, keyed by file name
+    pub fn source_units(&self) -> Result<BTreeMap<String, SourceUnit>, SolcError> {
+        let mut units = BTreeMap::new();
+        for (file, source) in self.sources.iter().flatten() {
+            if let Some(unit) = source.source_unit()? {
+                units.insert(file.clone(), unit);
+            }
+        }
+        Ok(units)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parameters(id: i64, names: &[&str]) -> serde_json::Value {
+        let parameters: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                json!({
+                    "nodeType": "VariableDeclaration", "id": id + 1 + i as i64, "src": "0:0:0",
+                    "name": name, "visibility": "internal", "stateVariable": false,
+                    "typeDescriptions": { "typeIdentifier": "t_uint256", "typeString": "uint256" }
+                })
+            })
+            .collect();
+        json!({ "nodeType": "ParameterList", "id": id, "src": "0:0:0", "parameters": parameters })
+    }
+
+    fn counter_ast() -> serde_json::Value {
+        let count = json!({
+            "nodeType": "VariableDeclaration", "id": 4, "src": "90:20:0", "name": "count",
+            "visibility": "public", "stateVariable": true, "constant": false,
+            "mutability": "mutable", "functionSelector": "06661abd",
+            "typeDescriptions": { "typeIdentifier": "t_uint256", "typeString": "uint256" }
+        });
+        let add = json!({
+            "nodeType": "FunctionDefinition", "id": 20, "src": "120:80:0", "name": "add",
+            "kind": "function", "visibility": "external", "stateMutability": "nonpayable",
+            "virtual": false, "implemented": true, "functionSelector": "1003e2d2",
+            "documentation": { "nodeType": "StructuredDocumentation", "id": 5, "src": "112:8:0", "text": "@notice Adds" },
+            "parameters": parameters(6, &["amount"]),
+            "returnParameters": parameters(8, &[]),
+            "modifiers": [],
+            "body": { "nodeType": "Block", "id": 19, "src": "180:20:0", "statements": [] }
+        });
+        let contract = json!({
+            "nodeType": "ContractDefinition", "id": 29, "src": "58:240:0", "name": "Counter",
+            "contractKind": "contract", "abstract": false, "baseContracts": [],
+            "linearizedBaseContracts": [29],
+            "documentation": { "nodeType": "StructuredDocumentation", "id": 2, "src": "58:20:0", "text": "@title A counter" },
+            "nodes": [count, add, { "nodeType": "UsingForDirective", "id": 21, "src": "210:10:0" }]
+        });
+        json!({
+            "nodeType": "SourceUnit", "id": 30, "src": "0:300:0",
+            "absolutePath": "src/Counter.sol", "license": "MIT",
+            "exportedSymbols": { "Counter": [29] },
+            "nodes": [
+                { "nodeType": "PragmaDirective", "id": 1, "src": "32:24:0", "literals": ["solidity", "^", "0.8", ".20"] },
+                contract
+            ]
+        })
+    }
+
+    #[test]
+    fn test_parses_compact_ast() {
+        let source = SolcSourceEntry { id: 0, ast: Some(counter_ast()) };
+        let unit = source.source_unit().unwrap().unwrap();
+        assert_eq!(unit.license.as_deref(), Some("MIT"));
+        assert_eq!(unit.src, Src { start: Some(0), length: Some(300), index: 0 });
+        let unknown: Src = serde_json::from_value(json!("-1:-1:-1")).unwrap();
+        assert_eq!(unknown, Src { start: None, length: None, index: -1 });
+
+        let contract = unit.contracts().next().unwrap();
+        assert_eq!(contract.contract_kind, ContractKind::Contract);
+        assert_eq!(contract.documentation.as_ref().unwrap().text(), "@title A counter");
+        assert_eq!(contract.state_variables().next().unwrap().mutability, Some(Mutability::Mutable));
+        assert!(matches!(contract.nodes[2], Node::Other(_)));
+
+        let add = contract.functions().next().unwrap();
+        assert_eq!(add.visibility, Visibility::External);
+        assert_eq!(add.function_selector.as_deref(), Some("1003e2d2"));
+        assert_eq!(add.parameters.parameters[0].name, "amount");
+        assert_eq!(
+            add.parameters.parameters[0].type_descriptions.type_string.as_deref(),
+            Some("uint256")
+        );
+
+        let broken = SolcSourceEntry { id: 0, ast: Some(json!({ "nodeType": "SourceUnit" })) };
+        assert!(matches!(broken.source_unit(), Err(SolcError::SerializationError(_))));
+    }
+
+    #[test]
+    fn test_visitor_walks_declarations() {
+        #[derive(Default)]
+        struct Collector {
+            functions: Vec<String>,
+            variables: Vec<String>,
+            natspec: Vec<String>,
+            others: usize,
+        }
+
+        impl Visitor for Collector {
+            fn visit_function(&mut self, function: &FunctionDefinition) {
+                self.functions.push(function.name.clone());
+                walk_function(self, function);
+            }
+
+            fn visit_variable(&mut self, variable: &VariableDeclaration) {
+                self.variables.push(variable.name.clone());
+            }
+
+            fn visit_documentation(&mut self, documentation: &Documentation) {
+                self.natspec.push(documentation.text().to_string());
+            }
+
+            fn visit_other(&mut self, _node: &serde_json::Value) {
+                self.others += 1;
+            }
+        }
+
+        let unit = SourceUnit::deserialize(counter_ast()).unwrap();
+        let mut collector = Collector::default();
+        collector.visit_source_unit(&unit);
+        assert_eq!(collector.functions, vec!["add"]);
+        assert_eq!(collector.variables, vec!["count", "amount"]);
+        assert_eq!(collector.natspec, vec!["@title A counter", "@notice Adds"]);
+        assert_eq!(collector.others, 1);
+    }
+}
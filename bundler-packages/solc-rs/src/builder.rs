@@ -0,0 +1,554 @@
+use crate::evm_version::EvmVersion;
+use crate::models::{
+    SolcDebugSettings, SolcInputDescription, SolcInputSource, SolcInputSources, SolcLanguage,
+    SolcMetadataSettings, SolcModelChecker, SolcOptimizer, SolcOptimizerDetails,
+    SolcOutputSelection, SolcSettings,
+};
+use std::collections::HashMap;
+
+/// A single output that can be requested through `settings.outputSelection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputSelectionItem {
+    /// Every output the compiler can generate (`*`)
+    All,
+    Ast,
+    Abi,
+    Devdoc,
+    Userdoc,
+    Metadata,
+    Ir,
+    IrOptimized,
+    StorageLayout,
+    Evm,
+    EvmAssembly,
+    EvmLegacyAssembly,
+    EvmBytecode,
+    EvmBytecodeFunctionDebugData,
+    EvmBytecodeObject,
+    EvmBytecodeOpcodes,
+    EvmBytecodeSourceMap,
+    EvmBytecodeLinkReferences,
+    EvmBytecodeGeneratedSources,
+    EvmDeployedBytecode,
+    EvmDeployedBytecodeFunctionDebugData,
+    EvmDeployedBytecodeObject,
+    EvmDeployedBytecodeOpcodes,
+    EvmDeployedBytecodeSourceMap,
+    EvmDeployedBytecodeLinkReferences,
+    EvmDeployedBytecodeGeneratedSources,
+    EvmDeployedBytecodeImmutableReferences,
+    EvmMethodIdentifiers,
+    EvmGasEstimates,
+    Ewasm,
+    EwasmWast,
+    EwasmWasm,
+}
+
+impl OutputSelectionItem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputSelectionItem::All => "*",
+            OutputSelectionItem::Ast => "ast",
+            OutputSelectionItem::Abi => "abi",
+            OutputSelectionItem::Devdoc => "devdoc",
+            OutputSelectionItem::Userdoc => "userdoc",
+            OutputSelectionItem::Metadata => "metadata",
+            OutputSelectionItem::Ir => "ir",
+            OutputSelectionItem::IrOptimized => "irOptimized",
+            OutputSelectionItem::StorageLayout => "storageLayout",
+            OutputSelectionItem::Evm => "evm",
+            OutputSelectionItem::EvmAssembly => "evm.assembly",
+            OutputSelectionItem::EvmLegacyAssembly => "evm.legacyAssembly",
+            OutputSelectionItem::EvmBytecode => "evm.bytecode",
+            OutputSelectionItem::EvmBytecodeFunctionDebugData => "evm.bytecode.functionDebugData",
+            OutputSelectionItem::EvmBytecodeObject => "evm.bytecode.object",
+            OutputSelectionItem::EvmBytecodeOpcodes => "evm.bytecode.opcodes",
+            OutputSelectionItem::EvmBytecodeSourceMap => "evm.bytecode.sourceMap",
+            OutputSelectionItem::EvmBytecodeLinkReferences => "evm.bytecode.linkReferences",
+            OutputSelectionItem::EvmBytecodeGeneratedSources => "evm.bytecode.generatedSources",
+            OutputSelectionItem::EvmDeployedBytecode => "evm.deployedBytecode",
+            OutputSelectionItem::EvmDeployedBytecodeFunctionDebugData => {
+                "evm.deployedBytecode.functionDebugData"
+            }
+            OutputSelectionItem::EvmDeployedBytecodeObject => "evm.deployedBytecode.object",
+            OutputSelectionItem::EvmDeployedBytecodeOpcodes => "evm.deployedBytecode.opcodes",
+            OutputSelectionItem::EvmDeployedBytecodeSourceMap => "evm.deployedBytecode.sourceMap",
+            OutputSelectionItem::EvmDeployedBytecodeLinkReferences => {
+                "evm.deployedBytecode.linkReferences"
+            }
+            OutputSelectionItem::EvmDeployedBytecodeGeneratedSources => {
+                "evm.deployedBytecode.generatedSources"
+            }
+            OutputSelectionItem::EvmDeployedBytecodeImmutableReferences => {
+                "evm.deployedBytecode.immutableReferences"
+            }
+            OutputSelectionItem::EvmMethodIdentifiers => "evm.methodIdentifiers",
+            OutputSelectionItem::EvmGasEstimates => "evm.gasEstimates",
+            OutputSelectionItem::Ewasm => "ewasm",
+            OutputSelectionItem::EwasmWast => "ewasm.wast",
+            OutputSelectionItem::EwasmWasm => "ewasm.wasm",
+        }
+    }
+
+    /// Outputs that belong to a whole source file and must be selected with an empty contract name
+    pub fn is_file_level(&self) -> bool {
+        matches!(self, OutputSelectionItem::Ast)
+    }
+}
+
+impl std::fmt::Display for OutputSelectionItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Common output selections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputSelectionPreset {
+    /// `abi` and `userdoc`, what the bundler requests by default
+    AbiOnly,
+    /// [`OutputSelectionPreset::AbiOnly`] plus creation and deployed bytecode (`includeBytecode`)
+    AbiBytecode,
+    /// [`OutputSelectionPreset::AbiBytecode`] plus the AST (`includeAst` and `includeBytecode`)
+    AbiBytecodeAst,
+    /// [`OutputSelectionPreset::AbiBytecodeAst`] plus source maps and `metadata`, what
+    /// `compileContractsSync.js` requests for `includeAst` and `includeBytecode`
+    AbiBytecodeAstSourceMaps,
+    /// Everything needed to debug and inspect a contract: docs, metadata, storage layout,
+    /// source maps, link/immutable references, method identifiers and the AST
+    FullDebug,
+}
+
+impl OutputSelectionPreset {
+    /// The preset the JS bundler uses for its `includeAst` / `includeBytecode` flags
+    ///
+    /// This follows `compileContracts.js`. `compileContractsSync.js` also selects source maps
+    /// and `metadata` when `includeAst` is set, see [`bundler_sync_output_selection`].
+    /// Requesting only the AST selects `abi`, `userdoc` and `ast`, which has no preset of its
+    /// own, so use [`bundler_output_selection`] when both flags are arbitrary.
+    pub fn from_bundler_flags(include_ast: bool, include_bytecode: bool) -> Option<Self> {
+        match (include_ast, include_bytecode) {
+            (false, false) => Some(OutputSelectionPreset::AbiOnly),
+            (false, true) => Some(OutputSelectionPreset::AbiBytecode),
+            (true, true) => Some(OutputSelectionPreset::AbiBytecodeAst),
+            (true, false) => None,
+        }
+    }
+
+    /// Outputs selected for every contract and, for file-level outputs, every source
+    pub fn items(&self) -> Vec<OutputSelectionItem> {
+        use OutputSelectionItem::*;
+        match self {
+            OutputSelectionPreset::AbiOnly => vec![Abi, Userdoc],
+            OutputSelectionPreset::AbiBytecode => {
+                vec![Abi, Userdoc, EvmBytecodeObject, EvmDeployedBytecodeObject]
+            }
+            OutputSelectionPreset::AbiBytecodeAst => vec![
+                Abi,
+                Userdoc,
+                EvmBytecodeObject,
+                EvmDeployedBytecodeObject,
+                Ast,
+            ],
+            OutputSelectionPreset::AbiBytecodeAstSourceMaps => vec![
+                Abi,
+                Userdoc,
+                EvmBytecodeObject,
+                EvmDeployedBytecodeObject,
+                EvmDeployedBytecodeSourceMap,
+                EvmBytecodeSourceMap,
+                Metadata,
+                Ast,
+            ],
+            OutputSelectionPreset::FullDebug => vec![
+                Abi,
+                Userdoc,
+                Devdoc,
+                Metadata,
+                StorageLayout,
+                EvmBytecode,
+                EvmDeployedBytecode,
+                EvmMethodIdentifiers,
+                Ast,
+            ],
+        }
+    }
+
+    pub fn output_selection(&self) -> SolcOutputSelection {
+        OutputSelectionBuilder::new().all(self.items()).build()
+    }
+}
+
+/// The output selection `compileContracts.js` builds for its `includeAst` / `includeBytecode` flags
+pub fn bundler_output_selection(include_ast: bool, include_bytecode: bool) -> SolcOutputSelection {
+    let mut items = vec![OutputSelectionItem::Abi, OutputSelectionItem::Userdoc];
+    if include_bytecode {
+        items.push(OutputSelectionItem::EvmBytecodeObject);
+        items.push(OutputSelectionItem::EvmDeployedBytecodeObject);
+    }
+    if include_ast {
+        items.push(OutputSelectionItem::Ast);
+    }
+    OutputSelectionBuilder::new().all(items).build()
+}
+
+/// The output selection `compileContractsSync.js` builds for its `includeAst` / `includeBytecode`
+/// flags
+///
+/// Unlike [`bundler_output_selection`], `includeAst` also selects both source maps and `metadata`.
+pub fn bundler_sync_output_selection(
+    include_ast: bool,
+    include_bytecode: bool,
+) -> SolcOutputSelection {
+    let mut items = vec![OutputSelectionItem::Abi, OutputSelectionItem::Userdoc];
+    if include_bytecode {
+        items.push(OutputSelectionItem::EvmBytecodeObject);
+        items.push(OutputSelectionItem::EvmDeployedBytecodeObject);
+    }
+    if include_ast {
+        items.push(OutputSelectionItem::EvmDeployedBytecodeSourceMap);
+        items.push(OutputSelectionItem::EvmBytecodeSourceMap);
+        items.push(OutputSelectionItem::Metadata);
+        items.push(OutputSelectionItem::Ast);
+    }
+    OutputSelectionBuilder::new().all(items).build()
+}
+
+/// Builds a [`SolcOutputSelection`] from typed [`OutputSelectionItem`]s
+///
+/// File-level items such as [`OutputSelectionItem::Ast`] are put under the empty contract
+/// name automatically.
+#[derive(Debug, Clone, Default)]
+pub struct OutputSelectionBuilder {
+    selection: SolcOutputSelection,
+}
+
+impl OutputSelectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select `items` for every contract in every file
+    pub fn all(self, items: impl IntoIterator<Item = OutputSelectionItem>) -> Self {
+        self.file("*", items)
+    }
+
+    /// Select `items` for every contract in `file`
+    pub fn file(self, file: &str, items: impl IntoIterator<Item = OutputSelectionItem>) -> Self {
+        self.contract(file, "*", items)
+    }
+
+    /// Select `items` for a single contract in `file`
+    pub fn contract(
+        mut self,
+        file: &str,
+        contract: &str,
+        items: impl IntoIterator<Item = OutputSelectionItem>,
+    ) -> Self {
+        let file_selection = self.selection.entry(file.to_string()).or_default();
+        for item in items {
+            let key = if item.is_file_level() { "" } else { contract };
+            let outputs = file_selection.entry(key.to_string()).or_default();
+            if !outputs.iter().any(|output| output == item.as_str()) {
+                outputs.push(item.as_str().to_string());
+            }
+        }
+        self
+    }
+
+    pub fn build(self) -> SolcOutputSelection {
+        self.selection
+    }
+}
+
+impl SolcSettings {
+    pub fn builder() -> SolcSettingsBuilder {
+        SolcSettingsBuilder::default()
+    }
+}
+
+/// Builder for [`SolcSettings`]
+#[derive(Debug, Clone, Default)]
+pub struct SolcSettingsBuilder {
+    settings: SolcSettings,
+}
+
+impl SolcSettingsBuilder {
+    /// Enable the optimizer tuned for `runs` executions
+    pub fn optimizer(mut self, runs: u32) -> Self {
+        let optimizer = self.settings.optimizer.get_or_insert_with(SolcOptimizer::default);
+        optimizer.enabled = Some(true);
+        optimizer.runs = runs;
+        self
+    }
+
+    /// Fine-tune individual optimizer steps
+    pub fn optimizer_details(mut self, details: SolcOptimizerDetails) -> Self {
+        self.settings
+            .optimizer
+            .get_or_insert_with(SolcOptimizer::default)
+            .details = Some(details);
+        self
+    }
+
+    pub fn evm_version(mut self, evm_version: EvmVersion) -> Self {
+        self.settings.evm_version = Some(evm_version);
+        self
+    }
+
+    pub fn via_ir(mut self, via_ir: bool) -> Self {
+        self.settings.via_ir = Some(via_ir);
+        self
+    }
+
+    /// Add a remapping such as `@openzeppelin/=lib/openzeppelin-contracts/`
+    pub fn remapping(mut self, remapping: impl Into<String>) -> Self {
+        self.settings
+            .remappings
+            .get_or_insert_with(Vec::new)
+            .push(remapping.into());
+        self
+    }
+
+    /// Link `library` declared in `file` to `address`
+    pub fn library(
+        mut self,
+        file: impl Into<String>,
+        library: impl Into<String>,
+        address: impl Into<String>,
+    ) -> Self {
+        self.settings
+            .libraries
+            .get_or_insert_with(HashMap::new)
+            .entry(file.into())
+            .or_default()
+            .insert(library.into(), address.into());
+        self
+    }
+
+    pub fn output_selection(mut self, output_selection: SolcOutputSelection) -> Self {
+        self.settings.output_selection = Some(output_selection);
+        self
+    }
+
+    pub fn preset(self, preset: OutputSelectionPreset) -> Self {
+        self.output_selection(preset.output_selection())
+    }
+
+    /// Only parse the sources, producing ASTs but no bytecode
+    pub fn stop_after_parsing(mut self) -> Self {
+        self.settings.stop_after = Some("parsing".to_string());
+        self
+    }
+
+    pub fn debug(mut self, debug: SolcDebugSettings) -> Self {
+        self.settings.debug = Some(debug);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: SolcMetadataSettings) -> Self {
+        self.settings.metadata = Some(metadata);
+        self
+    }
+
+    pub fn model_checker(mut self, model_checker: SolcModelChecker) -> Self {
+        self.settings.model_checker = Some(model_checker);
+        self
+    }
+
+    pub fn build(self) -> SolcSettings {
+        self.settings
+    }
+}
+
+impl SolcInputDescription {
+    pub fn builder() -> SolcInputDescriptionBuilder {
+        SolcInputDescriptionBuilder::default()
+    }
+}
+
+/// Builder for [`SolcInputDescription`], defaulting to Solidity
+#[derive(Debug, Clone)]
+pub struct SolcInputDescriptionBuilder {
+    language: SolcLanguage,
+    sources: SolcInputSources,
+    settings: Option<SolcSettings>,
+}
+
+impl Default for SolcInputDescriptionBuilder {
+    fn default() -> Self {
+        SolcInputDescriptionBuilder {
+            language: SolcLanguage::Solidity,
+            sources: HashMap::new(),
+            settings: None,
+        }
+    }
+}
+
+impl SolcInputDescriptionBuilder {
+    pub fn language(mut self, language: SolcLanguage) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Add a source file by its content
+    pub fn source(mut self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.sources.insert(
+            name.into(),
+            SolcInputSource {
+                keccak256: None,
+                urls: None,
+                content: Some(content.into()),
+                ast: None,
+            },
+        );
+        self
+    }
+
+    /// Add a source file solc should load from `urls`
+    pub fn source_urls(mut self, name: impl Into<String>, urls: Vec<String>) -> Self {
+        self.sources.insert(
+            name.into(),
+            SolcInputSource {
+                keccak256: None,
+                urls: Some(urls),
+                content: None,
+                ast: None,
+            },
+        );
+        self
+    }
+
+    /// Add a source file by its compact AST, for `SolidityAST` input
+    pub fn ast_source(mut self, name: impl Into<String>, ast: serde_json::Value) -> Self {
+        self.sources.insert(
+            name.into(),
+            SolcInputSource {
+                keccak256: None,
+                urls: None,
+                content: None,
+                ast: Some(ast),
+            },
+        );
+        self
+    }
+
+    pub fn sources(mut self, sources: SolcInputSources) -> Self {
+        self.sources.extend(sources);
+        self
+    }
+
+    pub fn settings(mut self, settings: SolcSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    pub fn build(self) -> SolcInputDescription {
+        SolcInputDescription {
+            language: self.language,
+            sources: self.sources,
+            settings: self.settings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bundler_output_selection_matches_js() {
+        assert_eq!(
+            serde_json::to_value(bundler_output_selection(false, false)).unwrap(),
+            json!({ "*": { "*": ["abi", "userdoc"] } })
+        );
+        assert_eq!(
+            serde_json::to_value(bundler_output_selection(true, true)).unwrap(),
+            json!({
+                "*": {
+                    "*": ["abi", "userdoc", "evm.bytecode.object", "evm.deployedBytecode.object"],
+                    "": ["ast"]
+                }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(bundler_output_selection(true, false)).unwrap(),
+            json!({ "*": { "*": ["abi", "userdoc"], "": ["ast"] } })
+        );
+        assert_eq!(
+            bundler_output_selection(false, true),
+            OutputSelectionPreset::AbiBytecode.output_selection()
+        );
+    }
+
+    #[test]
+    fn test_bundler_sync_output_selection_matches_js() {
+        assert_eq!(
+            serde_json::to_value(bundler_sync_output_selection(true, false)).unwrap(),
+            json!({
+                "*": {
+                    "*": [
+                        "abi",
+                        "userdoc",
+                        "evm.deployedBytecode.sourceMap",
+                        "evm.bytecode.sourceMap",
+                        "metadata"
+                    ],
+                    "": ["ast"]
+                }
+            })
+        );
+        assert_eq!(
+            bundler_sync_output_selection(true, true),
+            OutputSelectionPreset::AbiBytecodeAstSourceMaps.output_selection()
+        );
+        assert_eq!(
+            bundler_sync_output_selection(false, true),
+            bundler_output_selection(false, true)
+        );
+    }
+
+    #[test]
+    fn test_output_selection_builder_deduplicates() {
+        let selection = OutputSelectionBuilder::new()
+            .file("A.sol", [OutputSelectionItem::Abi, OutputSelectionItem::Abi])
+            .contract("A.sol", "A", [OutputSelectionItem::StorageLayout, OutputSelectionItem::Ast])
+            .build();
+        assert_eq!(
+            serde_json::to_value(selection).unwrap(),
+            json!({ "A.sol": { "*": ["abi"], "A": ["storageLayout"], "": ["ast"] } })
+        );
+    }
+
+    #[test]
+    fn test_input_description_builder() {
+        let input = SolcInputDescription::builder()
+            .source("A.sol", "contract A {}")
+            .settings(
+                SolcSettings::builder()
+                    .optimizer(200)
+                    .via_ir(true)
+                    .remapping("@oz/=lib/oz/")
+                    .library("Lib.sol", "Lib", "0x0000000000000000000000000000000000000001")
+                    .preset(OutputSelectionPreset::AbiOnly)
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(
+            serde_json::to_value(&input).unwrap(),
+            json!({
+                "language": "Solidity",
+                "sources": { "A.sol": { "content": "contract A {}" } },
+                "settings": {
+                    "optimizer": { "enabled": true, "runs": 200 },
+                    "viaIR": true,
+                    "remappings": ["@oz/=lib/oz/"],
+                    "libraries": { "Lib.sol": { "Lib": "0x0000000000000000000000000000000000000001" } },
+                    "outputSelection": { "*": { "*": ["abi", "userdoc"] } }
+                }
+            })
+        );
+    }
+}
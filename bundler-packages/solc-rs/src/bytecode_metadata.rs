@@ -0,0 +1,280 @@
+use crate::disassembler::{decode_object, metadata_trailer};
+use crate::error::SolcError;
+use crate::models::{SolcBytecodeOutput, SolcDeployedBytecodeOutput};
+use alloy_primitives::B256;
+
+/// The CBOR metadata solc appends to runtime bytecode
+///
+/// Which fields are present depends on `settings.metadata.bytecodeHash` and on whether
+/// the compiler is a release build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BytecodeMetadata {
+    /// Compiler version, `0.8.20` for releases or the full version string for prereleases
+    pub solc: Option<String>,
+    /// CIDv0 of the metadata JSON when `bytecodeHash` is `ipfs`
+    pub ipfs: Option<String>,
+    /// Swarm hash of the metadata JSON when `bytecodeHash` is `bzzr1`
+    pub bzzr1: Option<B256>,
+    /// Swarm hash emitted by solc < 0.6.0
+    pub bzzr0: Option<B256>,
+    /// Set when experimental features such as `pragma experimental SMTChecker` are enabled
+    pub experimental: bool,
+}
+
+impl BytecodeMetadata {
+    /// Decode the CBOR map of a metadata trailer, without the two byte length suffix
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self, SolcError> {
+        let mut reader = CborReader { data: cbor, pos: 0 };
+        let entries = match reader.next()? {
+            CborValue::Map(entries) => entries,
+            _ => return Err(invalid_cbor("metadata is not a map")),
+        };
+        let mut metadata = BytecodeMetadata::default();
+        for _ in 0..entries {
+            let key = match reader.next()? {
+                CborValue::Text(key) => key,
+                _ => return Err(invalid_cbor("metadata keys must be strings")),
+            };
+            let value = reader.next()?;
+            match (key, value) {
+                ("solc", CborValue::Bytes(&[major, minor, patch])) => {
+                    metadata.solc = Some(format!("{}.{}.{}", major, minor, patch));
+                }
+                ("solc", CborValue::Text(version)) => metadata.solc = Some(version.to_string()),
+                ("ipfs", CborValue::Bytes(multihash)) => metadata.ipfs = Some(bs58_encode(multihash)),
+                ("bzzr1", CborValue::Bytes(hash)) if hash.len() == 32 => {
+                    metadata.bzzr1 = Some(B256::from_slice(hash))
+                }
+                ("bzzr0", CborValue::Bytes(hash)) if hash.len() == 32 => {
+                    metadata.bzzr0 = Some(B256::from_slice(hash))
+                }
+                ("experimental", CborValue::Bool(experimental)) => metadata.experimental = experimental,
+                ("solc" | "ipfs" | "bzzr0" | "bzzr1" | "experimental", _) => {
+                    return Err(invalid_cbor(&format!("unexpected value for {}", key)))
+                }
+                (_, CborValue::Map(_)) => return Err(invalid_cbor("nested maps are not supported")),
+                // Newer compilers may add keys we do not know about yet
+                _ => {}
+            }
+        }
+        if reader.pos != cbor.len() {
+            return Err(invalid_cbor("trailing bytes after the metadata map"));
+        }
+        Ok(metadata)
+    }
+
+    /// Decode the metadata trailer at the end of `bytecode`
+    ///
+    /// Returns `None` if the bytecode has no trailer, e.g. when compiled with
+    /// `appendCBOR: false`. Creation bytecode with constructor arguments appended must be
+    /// split with [`crate::split_creation_code`] first.
+    pub fn from_bytecode(bytecode: &[u8]) -> Result<Option<Self>, SolcError> {
+        match metadata_trailer(bytecode) {
+            Some(range) => Self::from_cbor(&bytecode[range.start..range.end - 2]).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// `bytecode` without its CBOR metadata trailer
+pub fn strip_bytecode_metadata(bytecode: &[u8]) -> &[u8] {
+    match metadata_trailer(bytecode) {
+        Some(range) => &bytecode[..range.start],
+        None => bytecode,
+    }
+}
+
+impl SolcBytecodeOutput {
+    /// Decode the metadata trailer of `object`, which must be selected
+    pub fn bytecode_metadata(&self) -> Result<Option<BytecodeMetadata>, SolcError> {
+        object_metadata(self.object.as_deref())
+    }
+
+    /// `object` without its metadata trailer, keeping any library placeholders
+    ///
+    /// Two builds of the same code with different metadata hashes strip to the same string.
+    pub fn strip_metadata(&self) -> Result<String, SolcError> {
+        strip_object_metadata(self.object.as_deref())
+    }
+}
+
+impl SolcDeployedBytecodeOutput {
+    /// Decode the metadata trailer of `object`, which must be selected
+    pub fn bytecode_metadata(&self) -> Result<Option<BytecodeMetadata>, SolcError> {
+        object_metadata(self.object.as_deref())
+    }
+
+    /// `object` without its metadata trailer, keeping any library placeholders
+    pub fn strip_metadata(&self) -> Result<String, SolcError> {
+        strip_object_metadata(self.object.as_deref())
+    }
+}
+
+fn object_metadata(object: Option<&str>) -> Result<Option<BytecodeMetadata>, SolcError> {
+    let (bytecode, _) = decode_object(object.ok_or_else(not_selected)?)?;
+    BytecodeMetadata::from_bytecode(&bytecode)
+}
+
+fn strip_object_metadata(object: Option<&str>) -> Result<String, SolcError> {
+    let object = object.ok_or_else(not_selected)?;
+    let (bytecode, _) = decode_object(object)?;
+    let prefix = if object.starts_with("0x") { 2 } else { 0 };
+    // Placeholders are 40 characters for 20 bytes, so byte offsets map onto the hex string
+    Ok(match metadata_trailer(&bytecode) {
+        Some(range) => object[..prefix + range.start * 2].to_string(),
+        None => object.to_string(),
+    })
+}
+
+fn not_selected() -> SolcError {
+    SolcError::BytecodeError("Bytecode object was not selected".to_string())
+}
+
+fn invalid_cbor(reason: &str) -> SolcError {
+    SolcError::BytecodeError(format!("Invalid CBOR metadata: {}", reason))
+}
+
+/// The subset of CBOR solc uses for bytecode metadata
+enum CborValue<'a> {
+    Unsigned,
+    Bytes(&'a [u8]),
+    Text(&'a str),
+    Map(u64),
+    Bool(bool),
+}
+
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn next(&mut self) -> Result<CborValue<'a>, SolcError> {
+        let header = self.take(1)?[0];
+        let major = header >> 5;
+        let info = header & 0x1f;
+        if major == 7 {
+            return match info {
+                20 => Ok(CborValue::Bool(false)),
+                21 => Ok(CborValue::Bool(true)),
+                _ => Err(invalid_cbor("unsupported simple value")),
+            };
+        }
+        let argument = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err(invalid_cbor("indefinite lengths are not supported")),
+        };
+        match major {
+            0 => Ok(CborValue::Unsigned),
+            2 => Ok(CborValue::Bytes(self.take(argument as usize)?)),
+            3 => std::str::from_utf8(self.take(argument as usize)?)
+                .map(CborValue::Text)
+                .map_err(|_| invalid_cbor("text is not utf-8")),
+            5 => Ok(CborValue::Map(argument)),
+            _ => Err(invalid_cbor("unsupported major type")),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SolcError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid_cbor("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+/// Base58btc with the bitcoin alphabet, which is how CIDv0 multihashes are written
+fn bs58_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    let mut encoded = "1".repeat(zeros);
+    encoded.extend(digits.iter().rev().map(|&digit| ALPHABET[digit as usize] as char));
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runtime bytecode tail emitted by solc 0.8.20 with the default `bytecodeHash: ipfs`
+    const IPFS_TRAILER: &str = "a2646970667358221220\
+        e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\
+        64736f6c6343000814\
+        0033";
+
+    #[test]
+    fn test_decodes_and_strips_ipfs_metadata() {
+        let code = "6080604052348015600e575f80fd5b";
+        let object = format!("0x{}{}", code, IPFS_TRAILER);
+        let bytecode = SolcDeployedBytecodeOutput {
+            function_debug_data: None,
+            object: Some(object.clone()),
+            opcodes: None,
+            source_map: None,
+            generated_sources: None,
+            link_references: None,
+            immutable_references: None,
+        };
+
+        let metadata = bytecode.bytecode_metadata().unwrap().unwrap();
+        assert_eq!(metadata.solc.as_deref(), Some("0.8.20"));
+        // CIDv0 of the sha256 multihash of the empty string
+        assert_eq!(
+            metadata.ipfs.as_deref(),
+            Some("QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n")
+        );
+        assert!(!metadata.experimental);
+        assert_eq!(bytecode.strip_metadata().unwrap(), format!("0x{}", code));
+
+        let raw = hex::decode(&object[2..]).unwrap();
+        assert_eq!(strip_bytecode_metadata(&raw), hex::decode(code).unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_decodes_prerelease_and_experimental_flags() {
+        // {"bzzr1": <32 bytes>, "experimental": true, "solc": "0.8.21-ci"}
+        let mut cbor = vec![0xa3, 0x65];
+        cbor.extend(b"bzzr1");
+        cbor.extend([0x58, 0x20]);
+        cbor.extend([0x11; 32]);
+        cbor.push(0x6c);
+        cbor.extend(b"experimental");
+        cbor.push(0xf5);
+        cbor.push(0x64);
+        cbor.extend(b"solc");
+        cbor.push(0x69);
+        cbor.extend(b"0.8.21-ci");
+
+        let metadata = BytecodeMetadata::from_cbor(&cbor).unwrap();
+        assert_eq!(metadata.bzzr1, Some(B256::repeat_byte(0x11)));
+        assert!(metadata.experimental);
+        assert_eq!(metadata.solc.as_deref(), Some("0.8.21-ci"));
+
+        assert!(BytecodeMetadata::from_bytecode(&[0x60, 0x80]).unwrap().is_none());
+        assert!(matches!(
+            BytecodeMetadata::from_cbor(&cbor[..10]),
+            Err(SolcError::BytecodeError(_))
+        ));
+    }
+}
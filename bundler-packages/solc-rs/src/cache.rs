@@ -0,0 +1,400 @@
+use crate::error::SolcError;
+use crate::models::{SolcInputDescription, SolcOutput};
+use crate::solc::Solc;
+use alloy_primitives::{keccak256, B256};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable that overrides the default cache directory
+pub const SOLC_CACHE_ENV: &str = "TEVM_SOLC_CACHE";
+
+/// Bumped whenever cached outputs from older versions of this crate must not be reused
+const CACHE_FORMAT: u32 = 1;
+
+/// Stores compiled outputs so identical inputs do not spawn solc again
+pub trait CompileCache: Send + Sync + Debug {
+    /// The output cached under `key`, if any
+    fn get(&self, key: &CacheKey) -> Result<Option<SolcOutput>, SolcError>;
+
+    /// Cache `output` under `key`, replacing any earlier entry
+    fn put(&self, key: &CacheKey, output: &SolcOutput) -> Result<(), SolcError>;
+}
+
+/// Fingerprint of a compilation: the input, the solc version and the solc binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey(B256);
+
+impl CacheKey {
+    /// Fingerprint `input` as compiled by `solc`
+    ///
+    /// The input is serialized with object keys sorted and each source's `content` replaced
+    /// by its keccak256, so the key does not depend on field order. The binary is hashed
+    /// once per path and modification time.
+    pub fn new(solc: &Solc, input: &SolcInputDescription) -> Result<Self, SolcError> {
+        let mut input = serde_json::to_value(input)
+            .map_err(|e| SolcError::SerializationError(e.to_string()))?;
+        if let Some(sources) = input.get_mut("sources").and_then(Value::as_object_mut) {
+            for source in sources.values_mut().filter_map(Value::as_object_mut) {
+                if let Some(Value::String(content)) = source.remove("content") {
+                    let hash = format!("0x{}", hex::encode(keccak256(content.as_bytes())));
+                    source.insert("contentKeccak256".to_string(), Value::String(hash));
+                }
+            }
+        }
+        let fingerprint = serde_json::json!({
+            "cacheFormat": CACHE_FORMAT,
+            "input": input,
+            "solcKeccak256": format!("0x{}", hex::encode(binary_hash(&solc.path)?)),
+            "solcVersion": solc.version,
+        });
+        let mut canonical = String::new();
+        write_canonical(&fingerprint, &mut canonical);
+        Ok(CacheKey(keccak256(canonical.as_bytes())))
+    }
+
+    pub fn hash(&self) -> B256 {
+        self.0
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// JSON with object keys sorted at every level; array order is kept since it is meaningful
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Binary path to its modification time, size and keccak256
+type BinaryHashes = Mutex<HashMap<PathBuf, (SystemTime, u64, B256)>>;
+
+/// keccak256 of the binary at `path`, remembered until its size or modification time changes
+fn binary_hash(path: &Path) -> Result<B256, SolcError> {
+    static HASHES: OnceLock<BinaryHashes> = OnceLock::new();
+    let metadata = fs::metadata(path)?;
+    let (modified, len) = (metadata.modified()?, metadata.len());
+    let hashes = HASHES.get_or_init(Default::default);
+    if let Some((cached_modified, cached_len, hash)) =
+        hashes.lock().expect("binary hash lock poisoned").get(path)
+    {
+        if *cached_modified == modified && *cached_len == len {
+            return Ok(*hash);
+        }
+    }
+    let hash = keccak256(fs::read(path)?);
+    hashes
+        .lock()
+        .expect("binary hash lock poisoned")
+        .insert(path.to_path_buf(), (modified, len, hash));
+    Ok(hash)
+}
+
+/// Number and total size of the entries in a [`DiskCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// A [`CompileCache`] that keeps one JSON file per output in a directory
+///
+/// Reading an entry marks it as used. When a limit is set, writing an entry evicts the
+/// least recently used entries until the cache fits again.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
+}
+
+struct DiskEntry {
+    path: PathBuf,
+    bytes: u64,
+    last_used: SystemTime,
+}
+
+impl DiskCache {
+    /// Create a cache in `dir` without size limits; the directory is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache {
+            dir: dir.into(),
+            max_entries: None,
+            max_bytes: None,
+        }
+    }
+
+    /// The default cache, `$TEVM_SOLC_CACHE` or `~/.tevm/solc-cache`
+    pub fn default_cache() -> Result<Self, SolcError> {
+        if let Ok(dir) = std::env::var(SOLC_CACHE_ENV) {
+            return Ok(DiskCache::new(dir));
+        }
+        dirs::home_dir()
+            .map(|home| DiskCache::new(home.join(".tevm").join("solc-cache")))
+            .ok_or_else(|| SolcError::Unknown("Could not determine home directory".to_string()))
+    }
+
+    /// Keep at most `max_entries` outputs
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Keep at most `max_bytes` of outputs
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// The directory entries are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Entries from least to most recently used
+    fn entries(&self) -> Result<Vec<DiskEntry>, SolcError> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = vec![];
+        for entry in read_dir {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            entries.push(DiskEntry {
+                path,
+                bytes: metadata.len(),
+                last_used: metadata.modified()?,
+            });
+        }
+        entries.sort_by(|a, b| a.last_used.cmp(&b.last_used).then_with(|| a.path.cmp(&b.path)));
+        Ok(entries)
+    }
+
+    pub fn usage(&self) -> Result<CacheUsage, SolcError> {
+        let entries = self.entries()?;
+        Ok(CacheUsage {
+            entries: entries.len(),
+            bytes: entries.iter().map(|entry| entry.bytes).sum(),
+        })
+    }
+
+    /// Evict least recently used entries until the cache is within its limits
+    ///
+    /// Returns the number of entries removed.
+    pub fn prune(&self) -> Result<usize, SolcError> {
+        let entries = self.entries()?;
+        let mut count = entries.len();
+        let mut bytes: u64 = entries.iter().map(|entry| entry.bytes).sum();
+        let mut removed = 0;
+        for entry in entries {
+            let too_many = self.max_entries.is_some_and(|max| count > max);
+            let too_big = self.max_bytes.is_some_and(|max| bytes > max);
+            if !too_many && !too_big {
+                break;
+            }
+            remove_entry(&entry.path)?;
+            count -= 1;
+            bytes -= entry.bytes;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Remove entries that were not used within `max_age`, returning how many were removed
+    pub fn remove_unused_since(&self, max_age: Duration) -> Result<usize, SolcError> {
+        let cutoff = SystemTime::now()
+            .checked_sub(max_age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut removed = 0;
+        for entry in self.entries()? {
+            if entry.last_used >= cutoff {
+                break;
+            }
+            remove_entry(&entry.path)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) -> Result<(), SolcError> {
+        for entry in self.entries()? {
+            remove_entry(&entry.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl CompileCache for DiskCache {
+    fn get(&self, key: &CacheKey) -> Result<Option<SolcOutput>, SolcError> {
+        let path = self.entry_path(key);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match serde_json::from_str(&json) {
+            Ok(output) => {
+                // Recency only steers eviction, so a read-only cache still serves hits
+                let _ = File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.set_modified(SystemTime::now()));
+                Ok(Some(output))
+            }
+            // A truncated or outdated entry is a miss and gets rewritten after compiling
+            Err(_) => {
+                remove_entry(&path)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn put(&self, key: &CacheKey, output: &SolcOutput) -> Result<(), SolcError> {
+        let json = serde_json::to_vec(output)
+            .map_err(|e| SolcError::SerializationError(e.to_string()))?;
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so readers never see a partial entry
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(&json)?;
+        file.persist(self.entry_path(key)).map_err(|e| e.error)?;
+        self.prune()?;
+        Ok(())
+    }
+}
+
+/// Remove `path`, ignoring entries another process already removed
+fn remove_entry(path: &Path) -> Result<(), SolcError> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SolcSourceEntry;
+    use std::thread::sleep;
+
+    fn fake_solc(dir: &Path, binary: &str) -> Solc {
+        let path = dir.join("solc");
+        fs::write(&path, binary).unwrap();
+        Solc::new(path, "0.8.20".to_string())
+    }
+
+    fn output(id: u32) -> SolcOutput {
+        let mut sources = HashMap::new();
+        sources.insert("A.sol".to_string(), SolcSourceEntry { id, ast: None });
+        SolcOutput {
+            errors: None,
+            sources: Some(sources),
+            contracts: None,
+        }
+    }
+
+    #[test]
+    fn test_key_is_canonical() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), "binary v1");
+        let input = SolcInputDescription::builder()
+            .source("A.sol", "contract A {}")
+            .source("B.sol", "contract B {}")
+            .build();
+        let key = CacheKey::new(&solc, &input).unwrap();
+
+        // HashMap iteration order differs between instances, the key must not
+        let reordered = SolcInputDescription::builder()
+            .source("B.sol", "contract B {}")
+            .source("A.sol", "contract A {}")
+            .build();
+        assert_eq!(CacheKey::new(&solc, &reordered).unwrap(), key);
+
+        let edited = SolcInputDescription::builder()
+            .source("A.sol", "contract A { }")
+            .source("B.sol", "contract B {}")
+            .build();
+        assert_ne!(CacheKey::new(&solc, &edited).unwrap(), key);
+
+        let mut other_version = solc.clone();
+        other_version.version = "0.8.21".to_string();
+        assert_ne!(CacheKey::new(&other_version, &input).unwrap(), key);
+
+        let other_dir = tempfile::tempdir().unwrap();
+        let other_binary = fake_solc(other_dir.path(), "binary v2");
+        assert_ne!(CacheKey::new(&other_binary, &input).unwrap(), key);
+    }
+
+    #[test]
+    fn test_disk_cache_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path().join("cache")).with_max_entries(2);
+        let key = |byte: u8| CacheKey(B256::repeat_byte(byte));
+        assert_eq!(cache.get(&key(1)).unwrap(), None);
+
+        cache.put(&key(1), &output(1)).unwrap();
+        sleep(Duration::from_millis(20));
+        cache.put(&key(2), &output(2)).unwrap();
+        sleep(Duration::from_millis(20));
+        assert_eq!(cache.get(&key(1)).unwrap(), Some(output(1)));
+        sleep(Duration::from_millis(20));
+        cache.put(&key(3), &output(3)).unwrap();
+
+        assert_eq!(cache.get(&key(2)).unwrap(), None);
+        assert!(cache.get(&key(1)).unwrap().is_some());
+        assert_eq!(cache.usage().unwrap().entries, 2);
+
+        fs::write(cache.entry_path(&key(3)), "{ truncated").unwrap();
+        assert_eq!(cache.get(&key(3)).unwrap(), None);
+        assert_eq!(cache.usage().unwrap().entries, 1);
+
+        assert_eq!(cache.remove_unused_since(Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(cache.remove_unused_since(Duration::ZERO).unwrap(), 1);
+        cache.put(&key(4), &output(4)).unwrap();
+        cache.clear().unwrap();
+        assert_eq!(cache.usage().unwrap(), CacheUsage::default());
+    }
+}
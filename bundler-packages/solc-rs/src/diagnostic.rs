@@ -0,0 +1,391 @@
+use crate::models::{
+    SolcErrorEntry, SolcInputSources, SolcSecondarySourceLocation, SolcSourceLocation,
+};
+use std::fmt::Write;
+
+/// A 1-based line and column, with the column counted in characters rather than bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A range of [`LineColumn`]s, `end` being exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub start: LineColumn,
+    pub end: LineColumn,
+}
+
+/// Maps byte offsets in a source file to lines and columns
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// Byte offset at which every line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        LineIndex {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Number of lines in the source
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Line and column of a byte offset
+    ///
+    /// Offsets past the end of the source are clamped to it, and offsets inside a
+    /// multi-byte character resolve to that character.
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.line_starts[line]..offset].chars().count();
+        LineColumn {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Span covered by the byte range `start..end`
+    pub fn span(&self, start: usize, end: usize) -> SourceSpan {
+        SourceSpan {
+            start: self.line_column(start),
+            end: self.line_column(end.max(start)),
+        }
+    }
+
+    /// Text of a 1-based line without its line terminator
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|&next| next - 1)
+            .unwrap_or(self.source.len());
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+impl SolcSourceLocation {
+    /// Resolve the byte offsets to lines and columns using the compiled sources
+    pub fn span(&self, sources: &SolcInputSources) -> Option<SourceSpan> {
+        source_span(sources, &self.file, self.start, self.end)
+    }
+}
+
+impl SolcSecondarySourceLocation {
+    /// Resolve the byte offsets to lines and columns using the compiled sources
+    pub fn span(&self, sources: &SolcInputSources) -> Option<SourceSpan> {
+        source_span(sources, &self.file, self.start, self.end)
+    }
+}
+
+/// Resolve a byte range in `file` to lines and columns, if its content is available
+///
+/// Returns `None` for the `-1` offsets solc uses for locations without a range.
+pub fn source_span(
+    sources: &SolcInputSources,
+    file: &str,
+    start: i32,
+    end: i32,
+) -> Option<SourceSpan> {
+    let (start, end) = (usize::try_from(start).ok()?, usize::try_from(end).ok()?);
+    let content = sources.get(file)?.content.as_deref()?;
+    Some(LineIndex::new(content).span(start, end))
+}
+
+/// Render a solc diagnostic with a code snippet, in the style of rustc
+///
+/// ```text
+/// DeclarationError[7576]: Undeclared identifier.
+///  --> Contract.sol:4:9
+///   |
+/// 4 |         foo();
+///   |         ^^^
+/// ```
+///
+/// Secondary locations are rendered as `note:` sections after the primary snippet.
+/// Locations without a range or whose source content is not in `sources` are rendered
+/// without a snippet.
+pub fn render_diagnostic(entry: &SolcErrorEntry, sources: &SolcInputSources) -> String {
+    let mut out = String::new();
+    match &entry.error_code {
+        Some(code) => writeln!(out, "{}[{}]: {}", entry.error_type, code, entry.message),
+        None => writeln!(out, "{}: {}", entry.error_type, entry.message),
+    }
+    .unwrap();
+
+    if let Some(location) = &entry.source_location {
+        render_location(
+            &mut out,
+            sources,
+            &location.file,
+            location.start,
+            location.end,
+        );
+    }
+
+    for secondary in entry.secondary_source_locations.iter().flatten() {
+        writeln!(out, "note: {}", secondary.message).unwrap();
+        render_location(
+            &mut out,
+            sources,
+            &secondary.file,
+            secondary.start,
+            secondary.end,
+        );
+    }
+
+    out
+}
+
+/// Render several diagnostics separated by blank lines
+pub fn render_diagnostics(entries: &[SolcErrorEntry], sources: &SolcInputSources) -> String {
+    entries
+        .iter()
+        .map(|entry| render_diagnostic(entry, sources))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_location(out: &mut String, sources: &SolcInputSources, file: &str, start: i32, end: i32) {
+    let content = sources.get(file).and_then(|source| source.content.as_deref());
+    let (Some(content), Ok(start), Ok(end)) = (content, usize::try_from(start), usize::try_from(end)) else {
+        writeln!(out, " --> {}", file).unwrap();
+        return;
+    };
+    let index = LineIndex::new(content);
+    let span = index.span(start, end);
+    // A span ending at the start of a line covers nothing on it
+    let end_line = if span.end.line > span.start.line && span.end.column == 1 {
+        span.end.line - 1
+    } else {
+        span.end.line
+    };
+    let gutter = " ".repeat(end_line.to_string().len());
+
+    writeln!(out, "{}--> {}:{}:{}", gutter, file, span.start.line, span.start.column).unwrap();
+    writeln!(out, "{} |", gutter).unwrap();
+
+    let lines: Vec<usize> = if end_line - span.start.line > 3 {
+        // Keep long spans readable by only showing where they start and end
+        vec![span.start.line, 0, end_line]
+    } else {
+        (span.start.line..=end_line).collect()
+    };
+
+    for line in lines {
+        if line == 0 {
+            writeln!(out, "{} |", ".".repeat(gutter.len())).unwrap();
+            continue;
+        }
+        let text = index.line(line).unwrap_or_default();
+        let first = if line == span.start.line { span.start.column } else { 1 };
+        let last = if line == span.end.line {
+            span.end.column
+        } else {
+            text.chars().count() + 1
+        };
+        writeln!(out, "{:>width$} | {}", line, text, width = gutter.len()).unwrap();
+        writeln!(
+            out,
+            "{} | {}{}",
+            gutter,
+            caret_padding(text, first - 1),
+            "^".repeat(last.saturating_sub(first).max(1))
+        )
+        .unwrap();
+    }
+}
+
+/// Whitespace that lines carets up with the first `columns` characters of `text`
+fn caret_padding(text: &str, columns: usize) -> String {
+    text.chars()
+        .chain(std::iter::repeat(' '))
+        .take(columns)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SolcErrorComponent, SolcErrorType, SolcInputSource, SolcSeverity};
+    use std::collections::HashMap;
+
+    fn sources(file: &str, content: &str) -> SolcInputSources {
+        let mut sources = HashMap::new();
+        sources.insert(
+            file.to_string(),
+            SolcInputSource {
+                keccak256: None,
+                urls: None,
+                content: Some(content.to_string()),
+                ast: None,
+            },
+        );
+        sources
+    }
+
+    #[test]
+    fn test_line_column_counts_characters() {
+        let source = "// héllo\ncontract A {}\n";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_column(0), LineColumn { line: 1, column: 1 });
+        // `é` is two bytes but one column
+        assert_eq!(index.line_column(6), LineColumn { line: 1, column: 6 });
+        assert_eq!(index.line_column(10), LineColumn { line: 2, column: 1 });
+        // Offsets inside `é` resolve to it
+        assert_eq!(index.line_column(5), LineColumn { line: 1, column: 5 });
+        assert_eq!(index.line_column(4), LineColumn { line: 1, column: 5 });
+        assert_eq!(index.line_column(1000), LineColumn { line: 3, column: 1 });
+        assert_eq!(index.line(2), Some("contract A {}"));
+    }
+
+    #[test]
+    fn test_render_diagnostic_with_secondary_location() {
+        let source = "contract A {\n    uint x;\n    uint x;\n}\n";
+        let sources = sources("A.sol", source);
+        let entry = SolcErrorEntry {
+            source_location: Some(SolcSourceLocation {
+                file: "A.sol".to_string(),
+                start: 29,
+                end: 36,
+            }),
+            secondary_source_locations: Some(vec![SolcSecondarySourceLocation {
+                file: "A.sol".to_string(),
+                start: 17,
+                end: 23,
+                message: "The previous declaration is here:".to_string(),
+            }]),
+            error_type: SolcErrorType::DeclarationError,
+            component: SolcErrorComponent::General,
+            severity: SolcSeverity::Error,
+            error_code: Some("2333".to_string()),
+            message: "Identifier already declared.".to_string(),
+            formatted_message: None,
+        };
+
+        let expected = "\
+DeclarationError[2333]: Identifier already declared.
+ --> A.sol:3:5
+  |
+3 |     uint x;
+  |     ^^^^^^^
+note: The previous declaration is here:
+ --> A.sol:2:5
+  |
+2 |     uint x;
+  |     ^^^^^^
+";
+        assert_eq!(render_diagnostic(&entry, &sources), expected);
+    }
+
+    #[test]
+    fn test_render_diagnostic_without_content() {
+        let entry = SolcErrorEntry {
+            source_location: Some(SolcSourceLocation {
+                file: "Missing.sol".to_string(),
+                start: 0,
+                end: 1,
+            }),
+            secondary_source_locations: None,
+            error_type: SolcErrorType::Warning,
+            component: SolcErrorComponent::General,
+            severity: SolcSeverity::Warning,
+            error_code: None,
+            message: "Unused variable.".to_string(),
+            formatted_message: None,
+        };
+        assert_eq!(
+            render_diagnostic(&entry, &HashMap::new()),
+            "Warning: Unused variable.\n --> Missing.sol\n"
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostic_without_range() {
+        let entry: SolcErrorEntry = serde_json::from_str(
+            r#"{"type":"Warning","component":"general","severity":"warning","message":"Unreachable code.",
+                "sourceLocation":{"file":"A.sol","start":-1,"end":-1}}"#,
+        )
+        .unwrap();
+        let sources = sources("A.sol", "contract A {}");
+        let location = entry.source_location.as_ref().unwrap();
+        assert_eq!(location.start, -1);
+        assert!(location.span(&sources).is_none());
+        assert_eq!(
+            render_diagnostic(&entry, &sources),
+            "Warning: Unreachable code.\n --> A.sol\n"
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostic_over_several_lines() {
+        let source = "contract A {\n    function f() {\n        uint x;\n        x = 1;\n        x = 2;\n    }\n}\n";
+        let sources = sources("A.sol", source);
+        let render = |start: i32, end: i32| {
+            let entry: SolcErrorEntry = serde_json::from_value(serde_json::json!({
+                "type": "TypeError", "component": "general", "severity": "error",
+                "message": "Bad function.",
+                "sourceLocation": { "file": "A.sol", "start": start, "end": end }
+            }))
+            .unwrap();
+            render_diagnostic(&entry, &sources)
+        };
+
+        // Lines 2 to 4 are shown in full
+        assert_eq!(
+            render(17, 62),
+            "\
+TypeError: Bad function.
+ --> A.sol:2:5
+  |
+2 |     function f() {
+  |     ^^^^^^^^^^^^^^
+3 |         uint x;
+  | ^^^^^^^^^^^^^^^
+4 |         x = 1;
+  | ^^^^^^^^^^^^^^
+"
+        );
+
+        // Lines 2 to 6 only show where the span starts and ends
+        let elided = "\
+TypeError: Bad function.
+ --> A.sol:2:5
+  |
+2 |     function f() {
+  |     ^^^^^^^^^^^^^^
+. |
+6 |     }
+  | ^^^^^
+";
+        assert_eq!(render(17, 83), elided);
+        // Ending at the start of line 7 covers nothing on it
+        assert_eq!(render(17, 84), elided);
+        assert_eq!(
+            render(17, 32),
+            "\
+TypeError: Bad function.
+ --> A.sol:2:5
+  |
+2 |     function f() {
+  |     ^^^^^^^^^^^^^^
+"
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use crate::error::SolcError;
+use crate::models::{SolcBytecodeOutput, SolcDeployedBytecodeOutput};
+use alloy_primitives::U256;
+use std::fmt;
+use std::ops::Range;
+
+/// A single EVM opcode byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Opcode(pub u8);
+
+impl Opcode {
+    pub const STOP: Opcode = Opcode(0x00);
+    pub const JUMP: Opcode = Opcode(0x56);
+    pub const JUMPI: Opcode = Opcode(0x57);
+    pub const JUMPDEST: Opcode = Opcode(0x5b);
+    pub const PUSH0: Opcode = Opcode(0x5f);
+    pub const CODECOPY: Opcode = Opcode(0x39);
+    pub const RETURN: Opcode = Opcode(0xf3);
+    pub const REVERT: Opcode = Opcode(0xfd);
+    pub const INVALID: Opcode = Opcode(0xfe);
+
+    /// Mnemonic as printed by solc, `None` for unassigned opcodes
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.0 {
+            0x00 => "STOP",
+            0x01 => "ADD",
+            0x02 => "MUL",
+            0x03 => "SUB",
+            0x04 => "DIV",
+            0x05 => "SDIV",
+            0x06 => "MOD",
+            0x07 => "SMOD",
+            0x08 => "ADDMOD",
+            0x09 => "MULMOD",
+            0x0a => "EXP",
+            0x0b => "SIGNEXTEND",
+            0x10 => "LT",
+            0x11 => "GT",
+            0x12 => "SLT",
+            0x13 => "SGT",
+            0x14 => "EQ",
+            0x15 => "ISZERO",
+            0x16 => "AND",
+            0x17 => "OR",
+            0x18 => "XOR",
+            0x19 => "NOT",
+            0x1a => "BYTE",
+            0x1b => "SHL",
+            0x1c => "SHR",
+            0x1d => "SAR",
+            0x20 => "KECCAK256",
+            0x30 => "ADDRESS",
+            0x31 => "BALANCE",
+            0x32 => "ORIGIN",
+            0x33 => "CALLER",
+            0x34 => "CALLVALUE",
+            0x35 => "CALLDATALOAD",
+            0x36 => "CALLDATASIZE",
+            0x37 => "CALLDATACOPY",
+            0x38 => "CODESIZE",
+            0x39 => "CODECOPY",
+            0x3a => "GASPRICE",
+            0x3b => "EXTCODESIZE",
+            0x3c => "EXTCODECOPY",
+            0x3d => "RETURNDATASIZE",
+            0x3e => "RETURNDATACOPY",
+            0x3f => "EXTCODEHASH",
+            0x40 => "BLOCKHASH",
+            0x41 => "COINBASE",
+            0x42 => "TIMESTAMP",
+            0x43 => "NUMBER",
+            0x44 => "PREVRANDAO",
+            0x45 => "GASLIMIT",
+            0x46 => "CHAINID",
+            0x47 => "SELFBALANCE",
+            0x48 => "BASEFEE",
+            0x49 => "BLOBHASH",
+            0x4a => "BLOBBASEFEE",
+            0x50 => "POP",
+            0x51 => "MLOAD",
+            0x52 => "MSTORE",
+            0x53 => "MSTORE8",
+            0x54 => "SLOAD",
+            0x55 => "SSTORE",
+            0x56 => "JUMP",
+            0x57 => "JUMPI",
+            0x58 => "PC",
+            0x59 => "MSIZE",
+            0x5a => "GAS",
+            0x5b => "JUMPDEST",
+            0x5c => "TLOAD",
+            0x5d => "TSTORE",
+            0x5e => "MCOPY",
+            0x5f => "PUSH0",
+            0x60..=0x7f => PUSH_NAMES[(self.0 - 0x60) as usize],
+            0x80..=0x8f => DUP_NAMES[(self.0 - 0x80) as usize],
+            0x90..=0x9f => SWAP_NAMES[(self.0 - 0x90) as usize],
+            0xa0 => "LOG0",
+            0xa1 => "LOG1",
+            0xa2 => "LOG2",
+            0xa3 => "LOG3",
+            0xa4 => "LOG4",
+            0xf0 => "CREATE",
+            0xf1 => "CALL",
+            0xf2 => "CALLCODE",
+            0xf3 => "RETURN",
+            0xf4 => "DELEGATECALL",
+            0xf5 => "CREATE2",
+            0xfa => "STATICCALL",
+            0xfd => "REVERT",
+            0xfe => "INVALID",
+            0xff => "SELFDESTRUCT",
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Number of immediate bytes following the opcode (`PUSH1`..`PUSH32`)
+    pub fn push_size(&self) -> usize {
+        match self.0 {
+            0x60..=0x7f => (self.0 - 0x5f) as usize,
+            _ => 0,
+        }
+    }
+
+    /// `PUSH0`..`PUSH32`
+    pub fn is_push(&self) -> bool {
+        (0x5f..=0x7f).contains(&self.0)
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "0x{:02x}", self.0),
+        }
+    }
+}
+
+const PUSH_NAMES: [&str; 32] = [
+    "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10",
+    "PUSH11", "PUSH12", "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19",
+    "PUSH20", "PUSH21", "PUSH22", "PUSH23", "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28",
+    "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+];
+const DUP_NAMES: [&str; 16] = [
+    "DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11",
+    "DUP12", "DUP13", "DUP14", "DUP15", "DUP16",
+];
+const SWAP_NAMES: [&str; 16] = [
+    "SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10",
+    "SWAP11", "SWAP12", "SWAP13", "SWAP14", "SWAP15", "SWAP16",
+];
+
+/// A decoded instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub pc: usize,
+    pub opcode: Opcode,
+    /// Immediate bytes of a `PUSH`, truncated if the code ends early
+    pub push_data: Option<Vec<u8>>,
+    /// The `__$...$__` library placeholder this `PUSH20` carries in unlinked bytecode
+    pub placeholder: Option<String>,
+}
+
+impl Instruction {
+    /// Value pushed onto the stack by `PUSH0`..`PUSH32`
+    pub fn push_value(&self) -> Option<U256> {
+        if self.opcode == Opcode::PUSH0 {
+            return Some(U256::ZERO);
+        }
+        self.push_data
+            .as_ref()
+            .map(|data| U256::from_be_slice(data))
+    }
+
+    /// Size of the instruction including its immediate bytes
+    pub fn size(&self) -> usize {
+        1 + self.push_data.as_ref().map_or(0, Vec::len)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode)?;
+        if let Some(placeholder) = &self.placeholder {
+            write!(f, " {}", placeholder)
+        } else if let Some(data) = &self.push_data {
+            write!(f, " 0x{}", hex::encode(data))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Decoded bytecode: code instructions plus the CBOR metadata trailer, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub instructions: Vec<Instruction>,
+    /// Byte range of the CBOR metadata and its two byte length suffix
+    pub metadata: Option<Range<usize>>,
+}
+
+impl Disassembly {
+    /// Render in the same space separated format as solc's `opcodes` output
+    pub fn opcodes(&self) -> String {
+        self.instructions
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Decode every instruction in `bytecode`, including any trailing data
+pub fn disassemble(bytecode: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < bytecode.len() {
+        let opcode = Opcode(bytecode[pc]);
+        let push_size = opcode.push_size();
+        let push_data = (push_size > 0).then(|| {
+            let end = (pc + 1 + push_size).min(bytecode.len());
+            bytecode[pc + 1..end].to_vec()
+        });
+        instructions.push(Instruction {
+            pc,
+            opcode,
+            push_data,
+            placeholder: None,
+        });
+        pc += 1 + push_size;
+    }
+    instructions
+}
+
+/// Disassemble a solc `object`, linked or unlinked, stopping at the metadata trailer
+pub fn disassemble_object(object: &str) -> Result<Disassembly, SolcError> {
+    let (bytecode, placeholders) = decode_object(object)?;
+    let metadata = metadata_trailer(&bytecode);
+    let code_end = metadata.as_ref().map_or(bytecode.len(), |range| range.start);
+    let mut instructions = disassemble(&bytecode[..code_end]);
+    for instruction in instructions.iter_mut() {
+        let data_start = instruction.pc + 1;
+        instruction.placeholder = placeholders
+            .iter()
+            .find(|(offset, _)| *offset == data_start && instruction.opcode.push_size() == 20)
+            .map(|(_, placeholder)| placeholder.clone());
+    }
+    Ok(Disassembly {
+        instructions,
+        metadata,
+    })
+}
+
+/// Byte offsets of the `__$...$__` placeholders in an unlinked object
+pub type LibraryPlaceholders = Vec<(usize, String)>;
+
+/// Decode hex that may be `0x` prefixed and contain `__$...$__` library placeholders
+///
+/// Placeholders decode to zero bytes and are returned with their byte offset.
+pub fn decode_object(object: &str) -> Result<(Vec<u8>, LibraryPlaceholders), SolcError> {
+    let object = object.strip_prefix("0x").unwrap_or(object);
+    let mut normalized = String::with_capacity(object.len());
+    let mut placeholders = Vec::new();
+    let mut rest = object;
+    while let Some(start) = rest.find("__") {
+        let placeholder = rest
+            .get(start..start + 40)
+            .filter(|placeholder| placeholder.ends_with("__"))
+            .ok_or_else(|| {
+                SolcError::BytecodeError(format!("Malformed library placeholder in bytecode: {}", &rest[start..]))
+            })?;
+        normalized.push_str(&rest[..start]);
+        placeholders.push((normalized.len() / 2, placeholder.to_string()));
+        normalized.push_str(&"0".repeat(40));
+        rest = &rest[start + 40..];
+    }
+    normalized.push_str(rest);
+    let bytecode = hex::decode(&normalized)
+        .map_err(|e| SolcError::BytecodeError(format!("Invalid bytecode hex: {}", e)))?;
+    Ok((bytecode, placeholders))
+}
+
+/// Byte range of the CBOR metadata trailer solc appends, including the length suffix
+///
+/// The last two bytes hold the big-endian length of the CBOR map that precedes them.
+pub fn metadata_trailer(bytecode: &[u8]) -> Option<Range<usize>> {
+    let len = bytecode.len();
+    if len < 2 {
+        return None;
+    }
+    let cbor_len = u16::from_be_bytes([bytecode[len - 2], bytecode[len - 1]]) as usize;
+    let start = len.checked_sub(2 + cbor_len)?;
+    // A CBOR map header, which is what solc always emits
+    if cbor_len == 0 || !(0xa0..=0xbf).contains(&bytecode[start]) {
+        return None;
+    }
+    Some(start..len)
+}
+
+/// Byte ranges of the constructor and the runtime code inside creation bytecode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreationCodeSplit {
+    pub constructor: Range<usize>,
+    /// Runtime code including its metadata trailer
+    pub runtime: Range<usize>,
+    /// ABI encoded constructor arguments appended after the runtime code, if any
+    pub constructor_args: Range<usize>,
+}
+
+/// Split creation bytecode into constructor and runtime code
+///
+/// With `runtime` (the deployed bytecode of the same compilation) the split is exact.
+/// Without it the runtime offset is taken from the `PUSH offset PUSH 0 CODECOPY` sequence
+/// the legacy pipeline emits to return the runtime code. This is a heuristic for legacy
+/// code only: via-IR creation code copies to a memory pointer and returns `None`. The
+/// runtime code is taken to end at the first solc metadata trailer after its start, so
+/// it must have one for constructor arguments to be split off.
+pub fn split_creation_code(creation: &[u8], runtime: Option<&[u8]>) -> Option<CreationCodeSplit> {
+    let (runtime_start, runtime_end) = match runtime {
+        Some(runtime) if !runtime.is_empty() => {
+            let start = creation
+                .windows(runtime.len())
+                .position(|window| window == runtime)?;
+            (start, start + runtime.len())
+        }
+        Some(_) => return None,
+        None => {
+            let instructions = disassemble(creation);
+            let start = instructions.windows(3).find_map(|window| {
+                let [offset, dest, copy] = window else {
+                    return None;
+                };
+                if copy.opcode != Opcode::CODECOPY || dest.push_value()? != U256::ZERO {
+                    return None;
+                }
+                let offset: usize = offset.push_value()?.try_into().ok()?;
+                (offset > 0 && offset < creation.len()).then_some(offset)
+            })?;
+            // The runtime code ends with its own metadata trailer, if it has one. Searching
+            // forward keeps trailer-like bytes in constructor arguments from matching.
+            let end = (start..=creation.len())
+                .find(|&end| is_solc_trailer(&creation[start..end]))
+                .unwrap_or(creation.len());
+            (start, end)
+        }
+    };
+    Some(CreationCodeSplit {
+        constructor: 0..runtime_start,
+        runtime: runtime_start..runtime_end,
+        constructor_args: runtime_end..creation.len(),
+    })
+}
+
+/// Whether `code` ends with a metadata trailer holding one of the keys solc writes
+fn is_solc_trailer(code: &[u8]) -> bool {
+    const KEYS: [&[u8]; 4] = [b"\x64solc", b"\x64ipfs", b"\x65bzzr0", b"\x65bzzr1"];
+    metadata_trailer(code).is_some_and(|range| {
+        let cbor = &code[range];
+        KEYS.iter().any(|key| cbor.windows(key.len()).any(|window| window == *key))
+    })
+}
+
+impl SolcBytecodeOutput {
+    /// Disassemble `object`, which must be selected
+    pub fn disassemble(&self) -> Result<Disassembly, SolcError> {
+        disassemble_object(
+            self.object
+                .as_deref()
+                .ok_or_else(|| SolcError::BytecodeError("Bytecode object was not selected".to_string()))?,
+        )
+    }
+}
+
+impl SolcDeployedBytecodeOutput {
+    /// Disassemble `object`, which must be selected
+    pub fn disassemble(&self) -> Result<Disassembly, SolcError> {
+        disassemble_object(
+            self.object
+                .as_deref()
+                .ok_or_else(|| SolcError::BytecodeError("Bytecode object was not selected".to_string()))?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::library_placeholder;
+
+    const RUNTIME: &str = "60806040523480155b5ffe";
+    // {"solc": 0x000814}, followed by its length 0x000a
+    const TRAILER: &str = "a164736f6c6343000814000a";
+
+    #[test]
+    fn test_disassembles_with_metadata() {
+        let object = format!("{}{}", RUNTIME, TRAILER);
+        let disassembly = disassemble_object(&object).unwrap();
+        assert_eq!(
+            disassembly.opcodes(),
+            "PUSH1 0x80 PUSH1 0x40 MSTORE CALLVALUE DUP1 ISZERO JUMPDEST PUSH0 INVALID"
+        );
+        assert_eq!(disassembly.instructions[2].pc, 4);
+        assert_eq!(disassembly.instructions[1].push_value(), Some(U256::from(0x40)));
+        assert_eq!(disassembly.metadata, Some(11..23));
+    }
+
+    #[test]
+    fn test_disassembles_unlinked_object() {
+        let placeholder = library_placeholder("lib/Math.sol:Math");
+        let object = format!("0x73{}3b", placeholder);
+        let disassembly = disassemble_object(&object).unwrap();
+        assert_eq!(disassembly.instructions.len(), 2);
+        assert_eq!(disassembly.instructions[0].opcode.name(), Some("PUSH20"));
+        assert_eq!(disassembly.instructions[0].placeholder.as_deref(), Some(placeholder.as_str()));
+        assert_eq!(disassembly.instructions[0].push_value(), Some(U256::ZERO));
+        assert_eq!(disassembly.instructions[1].pc, 21);
+        assert_eq!(disassembly.opcodes(), format!("PUSH20 {} EXTCODESIZE", placeholder));
+        assert!(disassembly.metadata.is_none());
+    }
+
+    #[test]
+    fn test_truncated_push_and_unknown_opcode() {
+        let instructions = disassemble(&[0x0c, 0x61, 0x01]);
+        assert_eq!(instructions[0].opcode.to_string(), "0x0c");
+        assert_eq!(instructions[1].push_data, Some(vec![0x01]));
+    }
+
+    #[test]
+    fn test_splits_creation_code() {
+        let runtime = hex::decode(format!("{}{}", RUNTIME, TRAILER)).unwrap();
+        // PUSH1 len DUP1 PUSH1 offset PUSH0 CODECOPY PUSH0 RETURN INVALID
+        let constructor_len = 11u8;
+        let constructor = vec![
+            0x60,
+            runtime.len() as u8,
+            0x80,
+            0x60,
+            constructor_len,
+            0x5f,
+            0x39,
+            0x5f,
+            0xf3,
+            0xfe,
+            0x00,
+        ];
+        assert_eq!(constructor.len(), constructor_len as usize);
+        // An argument whose tail looks like a metadata trailer
+        let args = [vec![0u8; 20], hex::decode(TRAILER).unwrap()].concat();
+        let creation = [constructor.clone(), runtime.clone(), args].concat();
+
+        let expected = CreationCodeSplit {
+            constructor: 0..11,
+            runtime: 11..11 + runtime.len(),
+            constructor_args: 11 + runtime.len()..creation.len(),
+        };
+        assert_eq!(split_creation_code(&creation, Some(&runtime)), Some(expected.clone()));
+        assert_eq!(split_creation_code(&creation, None), Some(expected));
+    }
+
+    #[test]
+    fn test_split_needs_runtime_for_via_ir_code() {
+        let runtime = hex::decode(format!("{}{}", RUNTIME, TRAILER)).unwrap();
+        // PUSH1 0x40 MLOAD PUSH1 len DUP1 PUSH1 offset DUP4 CODECOPY DUP2 RETURN
+        let constructor = vec![0x60, 0x40, 0x51, 0x60, runtime.len() as u8, 0x80, 0x60, 12, 0x83, 0x39, 0x81, 0xf3];
+        let creation = [constructor, runtime.clone(), vec![0u8; 32]].concat();
+
+        assert_eq!(split_creation_code(&creation, None), None);
+        assert_eq!(
+            split_creation_code(&creation, Some(&runtime)),
+            Some(CreationCodeSplit {
+                constructor: 0..12,
+                runtime: 12..12 + runtime.len(),
+                constructor_args: 12 + runtime.len()..creation.len(),
+            })
+        );
+    }
+}
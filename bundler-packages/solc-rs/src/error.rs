@@ -1,4 +1,9 @@
+use crate::evm_version::EvmVersion;
+use crate::models::SolcErrorEntry;
+use crate::validate::SettingsIssue;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur when using the solc compiler
@@ -16,7 +21,133 @@ pub enum SolcError {
     #[error("Temporary file error: {0}")]
     TempFileError(#[from] io::Error),
     
+    /// The requested solc version is not in the store
+    #[error("solc {version} is not installed (expected it at {})", path.display())]
+    VersionNotInstalled { version: String, path: PathBuf },
+
+    /// A string is not a valid solc version
+    #[error("Invalid solc version: {0}")]
+    InvalidVersion(String),
+
+    /// The store has no keccak256/sha256 entry to verify a binary against
+    #[error("No checksum recorded for solc {0}")]
+    MissingChecksum(String),
+
+    /// A stored binary does not match its recorded checksum
+    #[error("Checksum mismatch for solc {version}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        version: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// `solc --version` reported a different version than requested
+    #[error("Version mismatch: expected solc {expected}, binary reports {actual}")]
+    VersionMismatch { expected: String, actual: String },
+
+    /// solc reported at least one error; holds every diagnostic, including warnings
+    #[error("{}", format_diagnostics(.0))]
+    Diagnostics(Vec<SolcErrorEntry>),
+
+    /// A string is not a known EVM version
+    #[error("Invalid EVM version: {0}")]
+    InvalidEvmVersion(String),
+
+    /// The selected EVM version is newer than the solc version can target
+    #[error("EVM version {evm_version} requires solc >= {min_solc_version}, but solc {solc_version} is used")]
+    UnsupportedEvmVersion {
+        evm_version: EvmVersion,
+        solc_version: String,
+        min_solc_version: String,
+    },
+
+    /// Library placeholders could not be replaced
+    #[error("Link error: {0}")]
+    LinkError(String),
+
+    /// Immutable values could not be resolved or written
+    #[error("Immutable error: {0}")]
+    ImmutableError(String),
+
+    /// A source map could not be decoded or applied
+    #[error("Source map error: {0}")]
+    SourceMapError(String),
+
+    /// The input does not fit its language or settings
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// A source given by `urls` could not be loaded
+    #[error("Source load error: {0}")]
+    SourceLoadError(String),
+
+    /// Loaded source content does not match the source's `keccak256`
+    #[error("keccak256 mismatch for source {file}: expected {expected}, got {actual}")]
+    SourceHashMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// A storage path could not be resolved against a storage layout
+    #[error("Storage layout error: {0}")]
+    StorageLayoutError(String),
+
+    /// A bytecode object could not be decoded
+    #[error("Bytecode error: {0}")]
+    BytecodeError(String),
+
+    /// Settings the selected solc version would reject; holds every issue found
+    #[error("Invalid settings: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    InvalidSettings(Vec<SettingsIssue>),
+
+    /// A `pragma solidity` version expression could not be parsed
+    #[error("Invalid pragma solidity: {0}")]
+    InvalidPragma(String),
+
+    /// No installed solc satisfies every `pragma solidity`; holds each conflicting file and its pragma
+    #[error("{}", format_pragma_conflicts(.0))]
+    IncompatiblePragmas(Vec<(String, String)>),
+
+    /// solc was killed after exceeding the configured timeout
+    #[error("solc timed out after {0:?}")]
+    Timeout(Duration),
+
     /// Unknown error
     #[error("Unknown error: {0}")]
     Unknown(String),
-}
\ No newline at end of file
+}
+
+impl SolcError {
+    /// The diagnostics solc reported, if this is a [`SolcError::Diagnostics`]
+    pub fn diagnostics(&self) -> Option<&[SolcErrorEntry]> {
+        match self {
+            SolcError::Diagnostics(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+fn format_diagnostics(entries: &[SolcErrorEntry]) -> String {
+    let error_count = entries.iter().filter(|entry| entry.is_error()).count();
+    let mut message = format!("Compilation failed with {} error(s)", error_count);
+    for entry in entries {
+        message.push('\n');
+        match &entry.formatted_message {
+            Some(formatted) => message.push_str(formatted.trim_end()),
+            None => message.push_str(&format!("{}: {}", entry.error_type, entry.message)),
+        }
+    }
+    message
+}
+
+fn format_pragma_conflicts(files: &[(String, String)]) -> String {
+    if files.is_empty() {
+        return "No solc is installed".to_string();
+    }
+    let files: Vec<String> = files
+        .iter()
+        .map(|(file, pragma)| format!("{} ({})", file, pragma))
+        .collect();
+    format!("No installed solc satisfies the pragmas of {}", files.join(", "))
+}
@@ -0,0 +1,175 @@
+use crate::error::SolcError;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Target EVM version accepted by `settings.evmVersion`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvmVersion {
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+    Prague,
+    Osaka,
+}
+
+impl EvmVersion {
+    /// Every EVM version, oldest first
+    pub const ALL: [EvmVersion; 14] = [
+        EvmVersion::Homestead,
+        EvmVersion::TangerineWhistle,
+        EvmVersion::SpuriousDragon,
+        EvmVersion::Byzantium,
+        EvmVersion::Constantinople,
+        EvmVersion::Petersburg,
+        EvmVersion::Istanbul,
+        EvmVersion::Berlin,
+        EvmVersion::London,
+        EvmVersion::Paris,
+        EvmVersion::Shanghai,
+        EvmVersion::Cancun,
+        EvmVersion::Prague,
+        EvmVersion::Osaka,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvmVersion::Homestead => "homestead",
+            EvmVersion::TangerineWhistle => "tangerineWhistle",
+            EvmVersion::SpuriousDragon => "spuriousDragon",
+            EvmVersion::Byzantium => "byzantium",
+            EvmVersion::Constantinople => "constantinople",
+            EvmVersion::Petersburg => "petersburg",
+            EvmVersion::Istanbul => "istanbul",
+            EvmVersion::Berlin => "berlin",
+            EvmVersion::London => "london",
+            EvmVersion::Paris => "paris",
+            EvmVersion::Shanghai => "shanghai",
+            EvmVersion::Cancun => "cancun",
+            EvmVersion::Prague => "prague",
+            EvmVersion::Osaka => "osaka",
+        }
+    }
+
+    /// The first solc release that accepts this EVM version
+    pub fn min_solc_version(&self) -> Version {
+        let (major, minor, patch) = match self {
+            // `evmVersion` itself was introduced in 0.4.21
+            EvmVersion::Homestead
+            | EvmVersion::TangerineWhistle
+            | EvmVersion::SpuriousDragon
+            | EvmVersion::Byzantium
+            | EvmVersion::Constantinople => (0, 4, 21),
+            EvmVersion::Petersburg => (0, 5, 5),
+            EvmVersion::Istanbul => (0, 5, 14),
+            EvmVersion::Berlin => (0, 8, 5),
+            EvmVersion::London => (0, 8, 7),
+            EvmVersion::Paris => (0, 8, 18),
+            EvmVersion::Shanghai => (0, 8, 20),
+            EvmVersion::Cancun => (0, 8, 24),
+            EvmVersion::Prague => (0, 8, 27),
+            EvmVersion::Osaka => (0, 8, 29),
+        };
+        Version::new(major, minor, patch)
+    }
+
+    /// Whether `solc_version` can compile for this EVM version
+    pub fn is_supported_by(&self, solc_version: &Version) -> bool {
+        strip_metadata(solc_version) >= self.min_solc_version()
+    }
+
+    /// Return [`SolcError::UnsupportedEvmVersion`] if `solc_version` cannot target this EVM version
+    pub fn check(&self, solc_version: &Version) -> Result<(), SolcError> {
+        if self.is_supported_by(solc_version) {
+            return Ok(());
+        }
+        Err(SolcError::UnsupportedEvmVersion {
+            evm_version: *self,
+            solc_version: solc_version.to_string(),
+            min_solc_version: self.min_solc_version().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for EvmVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EvmVersion {
+    type Err = SolcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EvmVersion::ALL
+            .into_iter()
+            .find(|version| version.as_str() == s)
+            .ok_or_else(|| SolcError::InvalidEvmVersion(s.to_string()))
+    }
+}
+
+/// Parse a solc version such as `0.8.20` or `0.8.20+commit.a1b79de6`
+pub fn parse_solc_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+/// Prereleases and build metadata should not affect feature checks
+fn strip_metadata(version: &Version) -> Version {
+    Version::new(version.major, version.minor, version.patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_names() {
+        assert_eq!(
+            serde_json::to_string(&EvmVersion::TangerineWhistle).unwrap(),
+            "\"tangerineWhistle\""
+        );
+        let version: EvmVersion = serde_json::from_str("\"shanghai\"").unwrap();
+        assert_eq!(version, EvmVersion::Shanghai);
+        assert!(serde_json::from_str::<EvmVersion>("\"shangai\"").is_err());
+        assert!(matches!(
+            "shangai".parse::<EvmVersion>(),
+            Err(SolcError::InvalidEvmVersion(_))
+        ));
+        for version in EvmVersion::ALL {
+            assert_eq!(version.as_str().parse::<EvmVersion>().unwrap(), version);
+        }
+    }
+
+    #[test]
+    fn test_rejects_versions_newer_than_the_compiler() {
+        let solc_0_8_19 = parse_solc_version("0.8.19").unwrap();
+        assert!(EvmVersion::Paris.check(&solc_0_8_19).is_ok());
+        match EvmVersion::Cancun.check(&solc_0_8_19) {
+            Err(SolcError::UnsupportedEvmVersion {
+                evm_version,
+                solc_version,
+                min_solc_version,
+            }) => {
+                assert_eq!(evm_version, EvmVersion::Cancun);
+                assert_eq!(solc_version, "0.8.19");
+                assert_eq!(min_solc_version, "0.8.24");
+            }
+            other => panic!("Expected UnsupportedEvmVersion, got {:?}", other),
+        }
+
+        let solc_0_8_24 = parse_solc_version("v0.8.24+commit.e11b9ed9").unwrap();
+        assert!(EvmVersion::Cancun.is_supported_by(&solc_0_8_24));
+        assert!(!EvmVersion::Prague.is_supported_by(&solc_0_8_24));
+    }
+}
@@ -1,5 +1,4 @@
-use tevm_solc_rs::{create_solc, SolcInputDescription, SolcLanguage, SolcInputSource, SolcSettings, SolcOptimizer};
-use std::collections::HashMap;
+use tevm_solc_rs::{create_solc, OutputSelectionPreset, SolcInputDescription, SolcSettings};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -24,39 +23,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     "#;
     
-    // Prepare input sources
-    let mut sources = HashMap::new();
-    sources.insert(
-        "SimpleStorage.sol".to_string(),
-        SolcInputSource {
-            keccak256: None,
-            urls: None,
-            content: Some(contract.to_string()),
-        },
-    );
-    
-    // Define optimizer settings
-    let optimizer = SolcOptimizer {
-        enabled: Some(true),
-        runs: 200,
-        details: None,
-    };
-    
-    // Create compiler settings
-    let settings = SolcSettings {
-        optimizer: Some(optimizer),
-        ..Default::default()
-    };
+    // Create compiler settings with the optimizer and the bundler's abi+bytecode outputs
+    let settings = SolcSettings::builder()
+        .optimizer(200)
+        .preset(OutputSelectionPreset::AbiBytecode)
+        .build();
     
     // Create the input description
-    let input = SolcInputDescription {
-        language: SolcLanguage::Solidity,
-        sources,
-        settings: Some(settings),
-    };
+    let input = SolcInputDescription::builder()
+        .source("SimpleStorage.sol", contract)
+        .settings(settings)
+        .build();
     
     // Compile the contract
-    let output = solc.compile(&input)?;
+    let output = solc.compile_async(&input).await?;
     
     // Check for errors
     if let Some(errors) = &output.errors {
@@ -0,0 +1,250 @@
+use crate::models::{GasEstimate, SolcGasEstimates, SolcOutput};
+use alloy_primitives::{keccak256, Selector};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+impl SolcGasEstimates {
+    /// External function estimates keyed by 4-byte selector instead of signature
+    ///
+    /// The fallback function has no selector and is left out.
+    pub fn external_by_selector(&self) -> HashMap<Selector, GasEstimate> {
+        self.external
+            .iter()
+            .flatten()
+            .filter(|(signature, _)| !signature.is_empty())
+            .map(|(signature, gas)| (function_selector(signature), *gas))
+            .collect()
+    }
+
+    /// Every estimate of the contract keyed by what it measures
+    pub fn entries(&self) -> BTreeMap<GasEstimateKey, GasEstimate> {
+        let mut entries = BTreeMap::new();
+        entries.insert(GasEstimateKey::Creation, self.creation.total_cost);
+        entries.insert(GasEstimateKey::CodeDeposit, self.creation.code_deposit_cost);
+        entries.insert(GasEstimateKey::Execution, self.creation.execution_cost);
+        for (signature, gas) in self.external.iter().flatten() {
+            entries.insert(GasEstimateKey::External(signature.clone()), *gas);
+        }
+        for (signature, gas) in self.internal.iter().flatten() {
+            entries.insert(GasEstimateKey::Internal(signature.clone()), *gas);
+        }
+        entries
+    }
+}
+
+/// The selector of a canonical signature such as `transfer(address,uint256)`
+pub fn function_selector(signature: &str) -> Selector {
+    Selector::from_slice(&keccak256(signature.as_bytes())[..4])
+}
+
+/// What a gas estimate measures
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GasEstimateKey {
+    /// Total deployment cost, code deposit included
+    Creation,
+    /// Cost of storing the deployed code
+    CodeDeposit,
+    /// Cost of running the constructor
+    Execution,
+    /// An external function by signature, `""` for the fallback function
+    External(String),
+    /// An internal function by signature
+    Internal(String),
+}
+
+impl GasEstimateKey {
+    pub fn selector(&self) -> Option<Selector> {
+        match self {
+            GasEstimateKey::External(signature) if !signature.is_empty() => {
+                Some(function_selector(signature))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GasEstimateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GasEstimateKey::Creation => f.write_str("creation"),
+            GasEstimateKey::CodeDeposit => f.write_str("creation code deposit"),
+            GasEstimateKey::Execution => f.write_str("creation execution"),
+            GasEstimateKey::External(signature) if signature.is_empty() => f.write_str("fallback"),
+            GasEstimateKey::External(signature) | GasEstimateKey::Internal(signature) => {
+                f.write_str(signature)
+            }
+        }
+    }
+}
+
+/// A gas estimate that differs between two compilations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasDiff {
+    pub file: String,
+    pub contract: String,
+    pub key: GasEstimateKey,
+    /// `None` if the function or contract did not exist before
+    pub before: Option<GasEstimate>,
+    /// `None` if the function or contract was removed
+    pub after: Option<GasEstimate>,
+}
+
+impl GasDiff {
+    /// Change in gas, if both estimates are finite
+    pub fn delta(&self) -> Option<i128> {
+        let before = self.before?.as_finite()?;
+        let after = self.after?.as_finite()?;
+        Some(after as i128 - before as i128)
+    }
+
+    /// Whether the estimate went up, including finite to infinite
+    pub fn is_regression(&self) -> bool {
+        matches!((self.before, self.after), (Some(before), Some(after)) if after > before)
+    }
+}
+
+impl fmt::Display for GasDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let estimate = |gas: Option<GasEstimate>| gas.map_or("-".to_string(), |gas| gas.to_string());
+        write!(
+            f,
+            "{}:{} {}: {} -> {}",
+            self.file,
+            self.contract,
+            self.key,
+            estimate(self.before),
+            estimate(self.after)
+        )?;
+        if let Some(delta) = self.delta() {
+            write!(f, " ({:+})", delta)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the `evm.gasEstimates` of two outputs, returning every estimate that changed
+///
+/// Contracts without gas estimates in either output are skipped. Results are sorted by file,
+/// contract and key.
+pub fn gas_diff(before: &SolcOutput, after: &SolcOutput) -> Vec<GasDiff> {
+    let before = contract_estimates(before);
+    let after = contract_estimates(after);
+    let mut contracts: Vec<_> = before.keys().chain(after.keys()).collect();
+    contracts.sort();
+    contracts.dedup();
+
+    let empty = BTreeMap::new();
+    let mut diffs = vec![];
+    for contract in contracts {
+        let old = before.get(contract).unwrap_or(&empty);
+        let new = after.get(contract).unwrap_or(&empty);
+        let mut keys: Vec<_> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let (old, new) = (old.get(key).copied(), new.get(key).copied());
+            if old != new {
+                diffs.push(GasDiff {
+                    file: contract.0.clone(),
+                    contract: contract.1.clone(),
+                    key: key.clone(),
+                    before: old,
+                    after: new,
+                });
+            }
+        }
+    }
+    diffs
+}
+
+type ContractEstimates = BTreeMap<(String, String), BTreeMap<GasEstimateKey, GasEstimate>>;
+
+fn contract_estimates(output: &SolcOutput) -> ContractEstimates {
+    let mut estimates = BTreeMap::new();
+    for (file, contracts) in output.contracts.iter().flatten() {
+        for (name, contract) in contracts {
+            let gas = contract
+                .evm
+                .as_ref()
+                .and_then(|evm| evm.gas_estimates.as_ref());
+            if let Some(gas) = gas {
+                estimates.insert((file.clone(), name.clone()), gas.entries());
+            }
+        }
+    }
+    estimates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output(transfer: &str, execution: &str, extra: Option<&str>) -> SolcOutput {
+        let total = (1000 + execution.parse::<u64>().unwrap()).to_string();
+        let mut external = json!({ "transfer(address,uint256)": transfer, "": "infinite" });
+        if let Some(signature) = extra {
+            external[signature] = json!("500");
+        }
+        serde_json::from_value(json!({
+            "contracts": {
+                "Token.sol": {
+                    "Token": {
+                        "evm": {
+                            "gasEstimates": {
+                                "creation": { "codeDepositCost": "1000", "executionCost": execution, "totalCost": total },
+                                "external": external,
+                                "internal": { "_move(address,address,uint256)": "infinite" }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parses_estimates() {
+        let output = output("21000", "200", None);
+        let gas = output.contracts.as_ref().unwrap()["Token.sol"]["Token"]
+            .evm
+            .as_ref()
+            .unwrap()
+            .gas_estimates
+            .clone()
+            .unwrap();
+        assert_eq!(gas.creation.total_cost, GasEstimate::Finite(1200));
+        assert!(gas.external.as_ref().unwrap()[""].is_infinite());
+
+        let by_selector = gas.external_by_selector();
+        let transfer: Selector = "0xa9059cbb".parse().unwrap();
+        assert_eq!(by_selector[&transfer], GasEstimate::Finite(21000));
+        assert_eq!(by_selector.len(), 1);
+
+        assert_eq!(serde_json::to_value(GasEstimate::Infinite).unwrap(), "infinite");
+        assert!(serde_json::from_value::<GasEstimate>(json!("lots")).is_err());
+    }
+
+    #[test]
+    fn test_diffs_outputs() {
+        let before = output("21000", "200", Some("burn(uint256)"));
+        let after = output("infinite", "100", Some("mint(uint256)"));
+        let diffs = gas_diff(&before, &after);
+        let summary: Vec<_> = diffs.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            summary,
+            vec![
+                "Token.sol:Token creation: 1200 -> 1100 (-100)",
+                "Token.sol:Token creation execution: 200 -> 100 (-100)",
+                "Token.sol:Token burn(uint256): 500 -> -",
+                "Token.sol:Token mint(uint256): - -> 500",
+                "Token.sol:Token transfer(address,uint256): 21000 -> infinite",
+            ]
+        );
+        assert!(!diffs[0].is_regression());
+        assert!(diffs[4].is_regression());
+        assert_eq!(diffs[4].key.selector(), Some("0xa9059cbb".parse().unwrap()));
+        assert!(gas_diff(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::models::{SolcInputDescription, SolcInputSource, SolcLanguage, SolcSettings};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A module of an import graph, in the shape resolutions-rs' `module_factory` returns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleInfo {
+    /// The code with imports rewritten to resolve remappings and node_modules
+    pub code: String,
+    /// The original, unmodified module source
+    pub raw_code: String,
+    /// The module ids statically imported by this module
+    pub imported_ids: Vec<PathBuf>,
+}
+
+/// Absolute module path to module, as built by resolutions-rs
+pub type ModuleGraph = HashMap<String, ModuleInfo>;
+
+/// Split `graph` into groups of modules connected by imports in either direction
+///
+/// Each group can be compiled on its own since none of its modules import, or are imported
+/// by, a module outside it. Imports of modules that are not in the graph are ignored. Ids are
+/// sorted within a group and groups by their first id.
+pub fn connected_components(graph: &ModuleGraph) -> Vec<Vec<String>> {
+    let mut ids: Vec<&String> = graph.keys().collect();
+    ids.sort();
+    let index: HashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+
+    let mut parents: Vec<usize> = (0..ids.len()).collect();
+    for (i, id) in ids.iter().enumerate() {
+        for imported in &graph[*id].imported_ids {
+            if let Some(&j) = index.get(imported.to_string_lossy().as_ref()) {
+                let (a, b) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut components: Vec<Vec<String>> = vec![];
+    let mut component_of_root: HashMap<usize, usize> = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        let root = find_root(&mut parents, i);
+        let component = *component_of_root.entry(root).or_insert_with(|| {
+            components.push(vec![]);
+            components.len() - 1
+        });
+        components[component].push(id.to_string());
+    }
+    components
+}
+
+/// Standard JSON input compiling the code of the modules `ids`
+pub fn input_description(
+    graph: &ModuleGraph,
+    ids: &[String],
+    settings: Option<SolcSettings>,
+) -> SolcInputDescription {
+    let sources = ids
+        .iter()
+        .map(|id| {
+            let source = SolcInputSource {
+                keccak256: None,
+                urls: None,
+                content: Some(graph[id].code.clone()),
+                ast: None,
+            };
+            (id.clone(), source)
+        })
+        .collect();
+    SolcInputDescription {
+        language: SolcLanguage::Solidity,
+        sources,
+        settings,
+    }
+}
+
+/// Union-find root of `i`, halving paths on the way
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(code: &str, imports: &[&str]) -> ModuleInfo {
+        ModuleInfo {
+            code: code.to_string(),
+            raw_code: code.to_string(),
+            imported_ids: imports.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let graph: ModuleGraph = [
+            ("/src/A.sol", module("", &["/src/lib/Math.sol"])),
+            ("/src/B.sol", module("", &["/lib/Ownable.sol"])),
+            ("/src/C.sol", module("", &["/src/lib/Math.sol", "/missing/X.sol"])),
+            ("/src/lib/Math.sol", module("", &[])),
+            ("/lib/Ownable.sol", module("", &[])),
+            ("/src/D.sol", module("", &[])),
+        ]
+        .into_iter()
+        .map(|(id, module)| (id.to_string(), module))
+        .collect();
+
+        assert_eq!(
+            connected_components(&graph),
+            vec![
+                vec!["/lib/Ownable.sol", "/src/B.sol"],
+                vec!["/src/A.sol", "/src/C.sol", "/src/lib/Math.sol"],
+                vec!["/src/D.sol"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::error::SolcError;
+use crate::models::{SolcDeployedBytecodeOutput, SolcOutput};
+use alloy_primitives::B256;
+use std::collections::HashMap;
+
+/// An `immutable` state variable declaration found in a source AST
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImmutableDeclaration {
+    /// AST id, the key of `immutableReferences`
+    pub id: u32,
+    pub name: String,
+    /// Name of the contract declaring the variable
+    pub contract: String,
+}
+
+impl SolcDeployedBytecodeOutput {
+    /// Write immutable values into `object` at every recorded `immutableReferences` offset
+    ///
+    /// Values are keyed by AST id and encoded as the 32-byte word solc stores, e.g.
+    /// `B256::from(U256::from(42))` or `address.into_word()`. Returns the ids that have
+    /// references but no value, whose slots are left untouched.
+    pub fn patch_immutables(&mut self, values: &HashMap<u32, B256>) -> Result<Vec<u32>, SolcError> {
+        let references = match &self.immutable_references {
+            Some(references) => references,
+            None => return Ok(vec![]),
+        };
+        let object = self.object.as_mut().ok_or_else(|| {
+            SolcError::ImmutableError("Bytecode has immutable references but no object".to_string())
+        })?;
+        let prefix = if object.starts_with("0x") { 2 } else { 0 };
+
+        let mut replacements = vec![];
+        let mut unpatched = vec![];
+        for (id, offsets) in references {
+            let id = id.parse::<u32>().map_err(|_| {
+                SolcError::ImmutableError(format!("Invalid immutable reference id: {}", id))
+            })?;
+            let value = match values.get(&id) {
+                Some(value) => hex::encode(value),
+                None => {
+                    unpatched.push(id);
+                    continue;
+                }
+            };
+            for offset in offsets {
+                let start = prefix + offset.start as usize * 2;
+                let end = start + offset.length as usize * 2;
+                if offset.length != 32 || object.get(start..end).is_none() {
+                    return Err(SolcError::ImmutableError(format!(
+                        "Immutable reference {} at byte {} does not fit the bytecode",
+                        id, offset.start
+                    )));
+                }
+                replacements.push((start..end, value.clone()));
+            }
+        }
+
+        for (range, value) in replacements {
+            object.replace_range(range, &value);
+        }
+        unpatched.sort_unstable();
+        Ok(unpatched)
+    }
+}
+
+/// Every `immutable` state variable declared in the ASTs of `output`
+///
+/// Requires the `ast` output to be selected.
+pub fn immutable_declarations(output: &SolcOutput) -> Vec<ImmutableDeclaration> {
+    let mut declarations = vec![];
+    for source in output.sources.iter().flat_map(|sources| sources.values()) {
+        if let Some(ast) = &source.ast {
+            collect_immutables(ast, None, &mut declarations);
+        }
+    }
+    declarations.sort_by_key(|declaration| declaration.id);
+    declarations
+}
+
+/// Resolve immutable values keyed by name to values keyed by AST id
+///
+/// Names are either `Contract.variable` or a bare `variable`; a bare name must be unique
+/// across all contracts in the output.
+pub fn resolve_immutable_values(
+    output: &SolcOutput,
+    values: &HashMap<String, B256>,
+) -> Result<HashMap<u32, B256>, SolcError> {
+    let declarations = immutable_declarations(output);
+    let mut resolved = HashMap::new();
+    for (name, value) in values {
+        let matches: Vec<_> = declarations
+            .iter()
+            .filter(|declaration| match name.split_once('.') {
+                Some((contract, variable)) => {
+                    declaration.contract == contract && declaration.name == variable
+                }
+                None => &declaration.name == name,
+            })
+            .collect();
+        match matches.as_slice() {
+            [declaration] => {
+                resolved.insert(declaration.id, *value);
+            }
+            [] => {
+                return Err(SolcError::ImmutableError(format!(
+                    "No immutable named {} in the AST",
+                    name
+                )))
+            }
+            _ => {
+                return Err(SolcError::ImmutableError(format!(
+                    "Immutable name {} is ambiguous, qualify it as Contract.{}",
+                    name, name
+                )))
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+fn collect_immutables(
+    node: &serde_json::Value,
+    contract: Option<&str>,
+    declarations: &mut Vec<ImmutableDeclaration>,
+) {
+    let node_type = node.get("nodeType").and_then(serde_json::Value::as_str);
+    let contract = match node_type {
+        Some("ContractDefinition") => node.get("name").and_then(serde_json::Value::as_str),
+        _ => contract,
+    };
+
+    if node_type == Some("VariableDeclaration")
+        && node.get("mutability").and_then(serde_json::Value::as_str) == Some("immutable")
+    {
+        // Ids that don't fit a u32 can't be patched, so they are skipped rather than truncated
+        // onto some other declaration's id
+        let id = node
+            .get("id")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|id| u32::try_from(id).ok());
+        let name = node.get("name").and_then(serde_json::Value::as_str);
+        if let (Some(id), Some(name), Some(contract)) = (id, name, contract) {
+            declarations.push(ImmutableDeclaration {
+                id,
+                name: name.to_string(),
+                contract: contract.to_string(),
+            });
+        }
+    }
+
+    for child in node.get("nodes").and_then(serde_json::Value::as_array).into_iter().flatten() {
+        collect_immutables(child, contract, declarations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SolcImmutableReference, SolcSourceEntry};
+    use alloy_primitives::{Address, U256};
+    use serde_json::json;
+
+    fn output_with_ast() -> SolcOutput {
+        let ast = json!({
+            "nodeType": "SourceUnit",
+            "nodes": [
+                { "nodeType": "PragmaDirective", "id": 1 },
+                {
+                    "nodeType": "ContractDefinition",
+                    "name": "Token",
+                    "id": 10,
+                    "nodes": [
+                        { "nodeType": "VariableDeclaration", "name": "owner", "id": 3, "mutability": "immutable" },
+                        { "nodeType": "VariableDeclaration", "name": "supply", "id": 5, "mutability": "immutable" },
+                        { "nodeType": "VariableDeclaration", "name": "name", "id": 7, "mutability": "mutable" }
+                    ]
+                },
+                {
+                    "nodeType": "ContractDefinition",
+                    "name": "Vault",
+                    "id": 20,
+                    "nodes": [
+                        { "nodeType": "VariableDeclaration", "name": "owner", "id": 15, "mutability": "immutable" }
+                    ]
+                }
+            ]
+        });
+        let mut sources = HashMap::new();
+        sources.insert("Token.sol".to_string(), SolcSourceEntry { id: 0, ast: Some(ast) });
+        SolcOutput {
+            errors: None,
+            sources: Some(sources),
+            contracts: None,
+        }
+    }
+
+    #[test]
+    fn test_resolves_immutables_by_name() {
+        let output = output_with_ast();
+        assert_eq!(
+            immutable_declarations(&output)
+                .iter()
+                .map(|declaration| (declaration.id, declaration.contract.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(3, "Token"), (5, "Token"), (15, "Vault")]
+        );
+
+        let mut values = HashMap::new();
+        values.insert("supply".to_string(), B256::from(U256::from(1000)));
+        values.insert("Vault.owner".to_string(), Address::repeat_byte(0xaa).into_word());
+        let resolved = resolve_immutable_values(&output, &values).unwrap();
+        assert_eq!(resolved[&5], B256::from(U256::from(1000)));
+        assert_eq!(resolved[&15], Address::repeat_byte(0xaa).into_word());
+
+        let mut ambiguous = HashMap::new();
+        ambiguous.insert("owner".to_string(), B256::ZERO);
+        assert!(matches!(
+            resolve_immutable_values(&output, &ambiguous),
+            Err(SolcError::ImmutableError(_))
+        ));
+    }
+
+    #[test]
+    fn test_skips_ids_that_do_not_fit() {
+        let mut output = output_with_ast();
+        let ast = output.sources.as_mut().unwrap().get_mut("Token.sol").unwrap();
+        let ast = ast.ast.as_mut().unwrap();
+        // 2^32 + 3 would truncate to the id of Token.owner
+        ast["nodes"][1]["nodes"][1]["id"] = json!(u64::from(u32::MAX) + 4);
+
+        assert_eq!(
+            immutable_declarations(&output)
+                .iter()
+                .map(|declaration| declaration.id)
+                .collect::<Vec<_>>(),
+            vec![3, 15]
+        );
+    }
+
+    #[test]
+    fn test_patches_every_reference() {
+        let mut references = HashMap::new();
+        references.insert(
+            "5".to_string(),
+            vec![
+                SolcImmutableReference { start: 1, length: 32 },
+                SolcImmutableReference { start: 34, length: 32 },
+            ],
+        );
+        references.insert("3".to_string(), vec![SolcImmutableReference { start: 67, length: 32 }]);
+        let zeros = "00".repeat(32);
+        let mut bytecode = SolcDeployedBytecodeOutput {
+            function_debug_data: None,
+            object: Some(format!("60{}5b{}5b{}00", zeros, zeros, zeros)),
+            opcodes: None,
+            source_map: None,
+            generated_sources: None,
+            link_references: None,
+            immutable_references: Some(references),
+        };
+
+        let mut values = HashMap::new();
+        values.insert(5, B256::from(U256::from(0x2a)));
+        assert_eq!(bytecode.patch_immutables(&values).unwrap(), vec![3]);
+
+        let word = format!("{}2a", "00".repeat(31));
+        assert_eq!(
+            bytecode.object.unwrap(),
+            format!("60{}5b{}5b{}00", word, word, zeros)
+        );
+    }
+}
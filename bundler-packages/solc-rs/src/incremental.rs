@@ -0,0 +1,522 @@
+use crate::error::SolcError;
+use crate::graph::{connected_components, input_description, ModuleGraph};
+use crate::models::{SolcFunctionDebugData, SolcGeneratedSource, SolcOutput, SolcSettings};
+use crate::solc::Solc;
+use alloy_primitives::{keccak256, B256};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// AST fields holding `start:length:sourceId` locations
+const SRC_FIELDS: [&str; 4] = ["src", "nameLocation", "nameLocations", "nativeSrc"];
+
+/// AST fields holding a node id or a list of them
+///
+/// `exportedSymbols` maps names to lists of ids and is handled separately.
+const NODE_ID_FIELDS: [&str; 13] = [
+    "id",
+    "referencedDeclaration",
+    "scope",
+    "sourceUnit",
+    "declaration",
+    "assignments",
+    "overloadedDeclarations",
+    "baseFunctions",
+    "baseModifiers",
+    "linearizedBaseContracts",
+    "contractDependencies",
+    "usedErrors",
+    "usedEvents",
+];
+
+/// How far [`SolcOutput::merge`] moves the source ids and AST node ids of the other output
+#[derive(Debug, Clone, Copy)]
+struct IdOffsets {
+    source: u32,
+    node: u32,
+}
+
+impl SolcOutput {
+    /// Add the errors, sources and contracts of `other`, which must come from other sources
+    ///
+    /// The source ids of `other`, including those of generated sources, are moved past the
+    /// highest id of this output so every id keeps naming one file, and its AST node ids are
+    /// moved past the highest node id the same way. Its ASTs, source maps, function debug data
+    /// and immutable references are rewritten to match. Negative ids, which solc uses for
+    /// builtins such as `require`, are left as they are.
+    pub fn merge(&mut self, mut other: SolcOutput) {
+        let offsets = IdOffsets {
+            source: self.next_source_id(),
+            node: self.next_node_id(),
+        };
+        if offsets.source > 0 || offsets.node > 0 {
+            other.shift_ids(offsets);
+        }
+        if let Some(errors) = other.errors {
+            self.errors.get_or_insert_with(Vec::new).extend(errors);
+        }
+        if let Some(sources) = other.sources {
+            self.sources.get_or_insert_with(HashMap::new).extend(sources);
+        }
+        if let Some(contracts) = other.contracts {
+            self.contracts.get_or_insert_with(HashMap::new).extend(contracts);
+        }
+    }
+
+    /// One past the highest id of `sources` and of every generated source
+    fn next_source_id(&self) -> u32 {
+        let sources = self.sources.iter().flatten().map(|(_, source)| source.id);
+        let generated = self
+            .contracts
+            .iter()
+            .flat_map(HashMap::values)
+            .flat_map(HashMap::values)
+            .filter_map(|contract| contract.evm.as_ref())
+            .flat_map(|evm| {
+                let bytecode = evm.bytecode.as_ref().and_then(|b| b.generated_sources.as_ref());
+                let deployed = evm
+                    .deployed_bytecode
+                    .as_ref()
+                    .and_then(|b| b.generated_sources.as_ref());
+                bytecode.into_iter().chain(deployed).flatten()
+            })
+            .map(|generated| generated.id);
+        sources.chain(generated).map(|id| id + 1).max().unwrap_or(0)
+    }
+
+    /// One past the highest AST node id of `sources`
+    fn next_node_id(&self) -> u32 {
+        self.sources
+            .iter()
+            .flat_map(HashMap::values)
+            .filter_map(|source| source.ast.as_ref())
+            .filter_map(max_node_id)
+            .map(|id| id + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Add the offsets to every source id and AST node id and every reference to one
+    fn shift_ids(&mut self, offsets: IdOffsets) {
+        for source in self.sources.iter_mut().flat_map(HashMap::values_mut) {
+            source.id += offsets.source;
+            if let Some(ast) = &mut source.ast {
+                shift_ast(ast, offsets);
+            }
+        }
+        let contracts = self
+            .contracts
+            .iter_mut()
+            .flat_map(HashMap::values_mut)
+            .flat_map(HashMap::values_mut);
+        for evm in contracts.filter_map(|contract| contract.evm.as_mut()) {
+            if let Some(bytecode) = &mut evm.bytecode {
+                shift_bytecode(
+                    &mut bytecode.source_map,
+                    &mut bytecode.generated_sources,
+                    &mut bytecode.function_debug_data,
+                    offsets,
+                );
+            }
+            if let Some(bytecode) = &mut evm.deployed_bytecode {
+                shift_bytecode(
+                    &mut bytecode.source_map,
+                    &mut bytecode.generated_sources,
+                    &mut bytecode.function_debug_data,
+                    offsets,
+                );
+                if let Some(references) = bytecode.immutable_references.take() {
+                    bytecode.immutable_references = Some(
+                        references
+                            .into_iter()
+                            .map(|(id, offsets_in_code)| {
+                                (shift_node_id_key(&id, offsets.node), offsets_in_code)
+                            })
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn shift_bytecode(
+    source_map: &mut Option<String>,
+    generated_sources: &mut Option<Vec<SolcGeneratedSource>>,
+    function_debug_data: &mut Option<HashMap<String, SolcFunctionDebugData>>,
+    offsets: IdOffsets,
+) {
+    if let Some(source_map) = source_map {
+        *source_map = shift_source_map(source_map, offsets.source);
+    }
+    for generated in generated_sources.iter_mut().flatten() {
+        generated.id += offsets.source;
+        shift_ast(&mut generated.ast, offsets);
+    }
+    for debug_data in function_debug_data.iter_mut().flat_map(HashMap::values_mut) {
+        if let Some(id) = &mut debug_data.id {
+            *id += offsets.node;
+        }
+    }
+}
+
+/// Shift an id used as a map key, such as the keys of `immutableReferences`
+fn shift_node_id_key(id: &str, offset: u32) -> String {
+    match id.parse::<u32>() {
+        Ok(id) => (id + offset).to_string(),
+        Err(_) => id.to_string(),
+    }
+}
+
+/// The highest non-negative `id` of any node in `node`
+fn max_node_id(node: &Value) -> Option<u32> {
+    match node {
+        Value::Object(fields) => {
+            let own = fields
+                .get("id")
+                .and_then(Value::as_u64)
+                .and_then(|id| u32::try_from(id).ok());
+            fields.values().filter_map(max_node_id).chain(own).max()
+        }
+        Value::Array(items) => items.iter().filter_map(max_node_id).max(),
+        _ => None,
+    }
+}
+
+/// Shift the source id field of each entry of a compressed source map
+///
+/// Empty fields inherit the previous, already shifted, id and `-1` stays internal code.
+fn shift_source_map(source_map: &str, offset: u32) -> String {
+    source_map
+        .split(';')
+        .map(|entry| {
+            entry
+                .split(':')
+                .enumerate()
+                .map(|(field, value)| match (field, value.parse::<u32>()) {
+                    (2, Ok(id)) => (id + offset).to_string(),
+                    _ => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn shift_ast(node: &mut Value, offsets: IdOffsets) {
+    match node {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if SRC_FIELDS.contains(&key.as_str()) {
+                    shift_src(value, offsets.source);
+                } else if NODE_ID_FIELDS.contains(&key.as_str()) {
+                    shift_node_ids(value, offsets.node);
+                } else if key == "exportedSymbols" {
+                    if let Value::Object(symbols) = value {
+                        symbols
+                            .values_mut()
+                            .for_each(|ids| shift_node_ids(ids, offsets.node));
+                    }
+                } else {
+                    shift_ast(value, offsets);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| shift_ast(item, offsets)),
+        _ => {}
+    }
+}
+
+/// Shift a non-negative node id, or each of an array of them
+fn shift_node_ids(value: &mut Value, offset: u32) {
+    match value {
+        Value::Number(id) => {
+            if let Some(shifted) = id.as_u64().map(|id| id + u64::from(offset)) {
+                *value = Value::from(shifted);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| shift_node_ids(item, offset)),
+        _ => {}
+    }
+}
+
+/// Shift a `start:length:sourceId` string, or each of an array of them
+fn shift_src(value: &mut Value, offset: u32) {
+    match value {
+        Value::String(src) => {
+            if let Some((range, id)) = src.rsplit_once(':') {
+                if let Ok(id) = id.parse::<u32>() {
+                    *src = format!("{}:{}", range, id + offset);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| shift_src(item, offset)),
+        _ => {}
+    }
+}
+
+/// Recompiles only the parts of a module graph that changed since the last compilation
+///
+/// The graph is split into [`connected_components`], each compiled as its own standard JSON
+/// input with the same settings. Outputs are kept per component, keyed by a hash of the ids
+/// and code of its modules, and merged into one [`SolcOutput`] with [`SolcOutput::merge`],
+/// which keeps source ids unique across components.
+#[derive(Debug)]
+pub struct IncrementalCompiler {
+    solc: Solc,
+    settings: Option<SolcSettings>,
+    outputs: HashMap<B256, SolcOutput>,
+    recompiled: Vec<String>,
+}
+
+impl IncrementalCompiler {
+    pub fn new(solc: Solc, settings: Option<SolcSettings>) -> Self {
+        IncrementalCompiler {
+            solc,
+            settings,
+            outputs: HashMap::new(),
+            recompiled: Vec::new(),
+        }
+    }
+
+    /// Compile `graph`, reusing the output of every component whose modules did not change
+    ///
+    /// Outputs of components that are no longer in the graph are dropped. Components that
+    /// fail to compile are compiled again on the next call; if any fails, the error carries
+    /// the diagnostics of every failed component.
+    pub async fn compile(&mut self, graph: &ModuleGraph) -> Result<SolcOutput, SolcError> {
+        self.recompiled.clear();
+        let mut order = vec![];
+        let mut outputs = HashMap::new();
+        let mut diagnostics = vec![];
+        for component in connected_components(graph) {
+            let hash = component_hash(graph, &component);
+            order.push(hash);
+            if let Some(output) = self.outputs.remove(&hash) {
+                outputs.insert(hash, output);
+                continue;
+            }
+            self.recompiled.extend(component.iter().cloned());
+            let input = input_description(graph, &component, self.settings.clone());
+            match self.solc.compile_async(&input).await {
+                Ok(output) => {
+                    outputs.insert(hash, output);
+                }
+                Err(SolcError::Diagnostics(entries)) => diagnostics.extend(entries),
+                Err(err) => {
+                    // Keep the reused outputs for the next call
+                    self.outputs.extend(outputs);
+                    return Err(err);
+                }
+            }
+        }
+        self.outputs = outputs;
+        if !diagnostics.is_empty() {
+            return Err(SolcError::Diagnostics(diagnostics));
+        }
+
+        let mut merged = SolcOutput {
+            errors: None,
+            sources: None,
+            contracts: None,
+        };
+        for hash in order {
+            merged.merge(self.outputs[&hash].clone());
+        }
+        Ok(merged)
+    }
+
+    /// Module ids compiled by the last call to [`IncrementalCompiler::compile`]
+    pub fn recompiled(&self) -> &[String] {
+        &self.recompiled
+    }
+}
+
+/// Hash of the ids and code of a component's modules, which covers every transitive import
+fn component_hash(graph: &ModuleGraph, component: &[String]) -> B256 {
+    let mut bytes = vec![];
+    for id in component {
+        for part in [id.as_bytes(), graph[id].code.as_bytes()] {
+            bytes.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(part);
+        }
+    }
+    keccak256(bytes)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::graph::ModuleInfo;
+    use crate::models::SolcInputDescription;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    /// A solc that saves each input as `run-<n>.json` and reports no output
+    fn recording_solc(dir: &Path) -> Solc {
+        let path = dir.join("solc");
+        let script = format!(
+            "#!/bin/sh\nn=$(ls {dir} | grep -c '^run-')\ncat > {dir}/run-$n.json\necho '{{}}'\n",
+            dir = dir.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Solc::new(path, "0.8.20".to_string())
+    }
+
+    fn runs(dir: &Path) -> Vec<Vec<String>> {
+        let mut runs = vec![];
+        while let Ok(json) = std::fs::read_to_string(dir.join(format!("run-{}.json", runs.len()))) {
+            let input: SolcInputDescription = serde_json::from_str(&json).unwrap();
+            let mut files: Vec<String> = input.sources.into_keys().collect();
+            files.sort();
+            runs.push(files);
+        }
+        runs
+    }
+
+    fn graph(math: &str) -> ModuleGraph {
+        let module = |code: &str, imports: &[&str]| ModuleInfo {
+            code: code.to_string(),
+            raw_code: code.to_string(),
+            imported_ids: imports.iter().map(PathBuf::from).collect(),
+        };
+        [
+            ("/src/A.sol", module("import './Math.sol'; contract A {}", &["/src/Math.sol"])),
+            ("/src/Math.sol", module(math, &[])),
+            ("/src/B.sol", module("contract B {}", &[])),
+        ]
+        .into_iter()
+        .map(|(id, module)| (id.to_string(), module))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_recompiles_changed_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut compiler = IncrementalCompiler::new(recording_solc(dir.path()), None);
+
+        compiler.compile(&graph("library Math {}")).await.unwrap();
+        assert_eq!(
+            runs(dir.path()),
+            vec![vec!["/src/A.sol", "/src/Math.sol"], vec!["/src/B.sol"]]
+        );
+
+        compiler.compile(&graph("library Math {}")).await.unwrap();
+        assert!(compiler.recompiled().is_empty());
+        assert_eq!(runs(dir.path()).len(), 2);
+
+        // Changing an import recompiles the files that depend on it, and only those
+        compiler.compile(&graph("library Math { }")).await.unwrap();
+        assert_eq!(compiler.recompiled(), ["/src/A.sol", "/src/Math.sol"]);
+        assert_eq!(runs(dir.path())[2], vec!["/src/A.sol", "/src/Math.sol"]);
+    }
+
+    #[test]
+    fn test_merges_outputs() {
+        let output = |file: &str| -> SolcOutput {
+            serde_json::from_value(serde_json::json!({
+                "sources": { file: { "id": 0, "ast": { "src": "0:20:0", "nodes": [
+                    { "src": "5:10:0", "nameLocation": "7:1:0", "pathNode": { "nameLocations": ["9:1:0"] } }
+                ] } } },
+                "contracts": { file: { "C": { "evm": { "deployedBytecode": {
+                    "sourceMap": "1:2:0:-;:3;4:5:-1;;6:1:1",
+                    "generatedSources": [{
+                        "ast": { "nodeType": "YulBlock", "src": "0:5:1" },
+                        "contents": "{}", "id": 1, "language": "Yul", "name": "#utility.yul"
+                    }]
+                } } } } }
+            }))
+            .unwrap()
+        };
+        let mut merged = output("A.sol");
+        merged.merge(output("B.sol"));
+        merged.merge(SolcOutput {
+            errors: None,
+            sources: None,
+            contracts: None,
+        });
+
+        let sources = merged.sources.as_ref().unwrap();
+        assert_eq!(sources["A.sol"].id, 0);
+        assert_eq!(sources["B.sol"].id, 2);
+        let ast = sources["B.sol"].ast.as_ref().unwrap();
+        assert_eq!(ast["src"], "0:20:2");
+        assert_eq!(ast["nodes"][0]["src"], "5:10:2");
+        assert_eq!(ast["nodes"][0]["nameLocation"], "7:1:2");
+        assert_eq!(ast["nodes"][0]["pathNode"]["nameLocations"][0], "9:1:2");
+
+        let contracts = merged.contracts.as_ref().unwrap();
+        let deployed = |file: &str| {
+            contracts[file]["C"].evm.as_ref().unwrap().deployed_bytecode.clone().unwrap()
+        };
+        assert_eq!(deployed("A.sol").source_map.unwrap(), "1:2:0:-;:3;4:5:-1;;6:1:1");
+        let b = deployed("B.sol");
+        assert_eq!(b.source_map.unwrap(), "1:2:2:-;:3;4:5:-1;;6:1:3");
+        let generated = &b.generated_sources.unwrap()[0];
+        assert_eq!(generated.id, 3);
+        assert_eq!(generated.ast["src"], "0:5:3");
+
+        let files = crate::source_map::source_files_by_id(&merged, None);
+        assert_eq!(files[&0], "A.sol");
+        assert_eq!(files[&2], "B.sol");
+    }
+
+    #[test]
+    fn test_merges_outputs_with_the_same_node_ids() {
+        let output = |file: &str| -> SolcOutput {
+            serde_json::from_value(serde_json::json!({
+                "sources": { file: { "id": 0, "ast": {
+                    "nodeType": "SourceUnit", "id": 11, "src": "0:50:0",
+                    "exportedSymbols": { "C": [10] },
+                    "nodes": [{
+                        "nodeType": "ContractDefinition", "id": 10, "name": "C", "src": "0:50:0",
+                        "scope": 11, "linearizedBaseContracts": [10],
+                        "nodes": [
+                            { "nodeType": "VariableDeclaration", "name": "owner", "id": 3,
+                              "scope": 10, "mutability": "immutable", "src": "10:5:0" },
+                            { "nodeType": "Identifier", "id": 4, "referencedDeclaration": 3, "src": "20:5:0" },
+                            { "nodeType": "Identifier", "id": 5, "referencedDeclaration": -18,
+                              "overloadedDeclarations": [-18, 2], "src": "30:7:0" }
+                        ]
+                    }]
+                } } },
+                "contracts": { file: { "C": { "evm": { "deployedBytecode": {
+                    "functionDebugData": { "@f_6": { "id": 6 } },
+                    "immutableReferences": { "3": [{ "start": 1, "length": 32 }] }
+                } } } } }
+            }))
+            .unwrap()
+        };
+        let mut merged = output("A.sol");
+        merged.merge(output("B.sol"));
+
+        let sources = merged.sources.as_ref().unwrap();
+        let a = sources["A.sol"].ast.as_ref().unwrap();
+        assert_eq!(a["id"], 11);
+        let b = sources["B.sol"].ast.as_ref().unwrap();
+        assert_eq!(b["id"], 23);
+        assert_eq!(b["exportedSymbols"]["C"][0], 22);
+        let contract = &b["nodes"][0];
+        assert_eq!(contract["id"], 22);
+        assert_eq!(contract["scope"], 23);
+        assert_eq!(contract["linearizedBaseContracts"][0], 22);
+        assert_eq!(contract["nodes"][0]["id"], 15);
+        assert_eq!(contract["nodes"][0]["scope"], 22);
+        assert_eq!(contract["nodes"][1]["referencedDeclaration"], 15);
+        assert_eq!(contract["nodes"][2]["referencedDeclaration"], -18);
+        assert_eq!(contract["nodes"][2]["overloadedDeclarations"], serde_json::json!([-18, 14]));
+
+        let contracts = merged.contracts.as_ref().unwrap();
+        let deployed = contracts["B.sol"]["C"].evm.as_ref().unwrap().deployed_bytecode.as_ref();
+        let deployed = deployed.unwrap();
+        assert_eq!(deployed.function_debug_data.as_ref().unwrap()["@f_6"].id, Some(18));
+        assert!(deployed.immutable_references.as_ref().unwrap().contains_key("15"));
+
+        let ids: Vec<_> = crate::immutables::immutable_declarations(&merged)
+            .into_iter()
+            .map(|declaration| declaration.id)
+            .collect();
+        assert_eq!(ids, [3, 15]);
+    }
+}
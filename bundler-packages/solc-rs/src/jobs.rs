@@ -0,0 +1,291 @@
+use crate::error::SolcError;
+use crate::evm_version::parse_solc_version;
+use crate::graph::{connected_components, input_description, ModuleGraph};
+use crate::models::{SolcInputDescription, SolcOutput, SolcSettings};
+use crate::pragma::select_solc_version;
+use crate::solc::Solc;
+use semver::Version;
+use std::collections::BTreeMap;
+use tokio::task::JoinSet;
+
+/// One solc invocation planned by a [`CompileJobSet`]
+#[derive(Debug, Clone)]
+pub struct CompileJob {
+    pub solc: Solc,
+    pub input: SolcInputDescription,
+}
+
+impl CompileJob {
+    /// Module ids compiled by this job, sorted
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.input.sources.keys().map(String::as_str).collect();
+        files.sort();
+        files
+    }
+}
+
+/// The merged result of every job in a [`CompileJobSet`]
+#[derive(Debug, Clone)]
+pub struct CompileJobSetOutput {
+    /// Outputs of all jobs, keyed by module id like a single compilation
+    ///
+    /// Every file is compiled by exactly one job, so file keys never collide. Outputs are
+    /// combined with [`SolcOutput::merge`], which renumbers source ids so they stay unique.
+    pub output: SolcOutput,
+    /// Module id to the version of the solc that compiled it
+    pub versions: BTreeMap<String, String>,
+}
+
+/// Compiles a module graph with several solc versions and settings in parallel
+///
+/// Files that import each other are always compiled together. Each such group gets the
+/// highest of the given compilers its `pragma solidity` ranges allow and the settings
+/// registered for its path; groups sharing both are compiled as one job.
+#[derive(Debug, Clone)]
+pub struct CompileJobSet {
+    compilers: Vec<Solc>,
+    settings: Option<SolcSettings>,
+    path_settings: Vec<(String, SolcSettings)>,
+    concurrency: usize,
+}
+
+impl CompileJobSet {
+    /// Plan jobs across `compilers`, running as many at once as there are CPUs
+    pub fn new(compilers: impl IntoIterator<Item = Solc>) -> Self {
+        CompileJobSet {
+            compilers: compilers.into_iter().collect(),
+            settings: None,
+            path_settings: Vec::new(),
+            concurrency: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+        }
+    }
+
+    /// Settings for files without more specific settings
+    pub fn with_settings(mut self, settings: SolcSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Settings for module ids starting with `prefix`; the longest matching prefix wins
+    pub fn with_settings_for(mut self, prefix: impl Into<String>, settings: SolcSettings) -> Self {
+        self.path_settings.push((prefix.into(), settings));
+        self
+    }
+
+    /// Run at most `concurrency` solc processes at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Index into `path_settings` of the settings for `id`, `None` for the default settings
+    fn settings_index(&self, id: &str) -> Option<usize> {
+        self.path_settings
+            .iter()
+            .enumerate()
+            .filter(|(_, (prefix, _))| id.starts_with(prefix.as_str()))
+            .max_by_key(|(_, (prefix, _))| prefix.len())
+            .map(|(i, _)| i)
+    }
+
+    /// Split `graph` into jobs, ordered by solc version and then settings
+    pub fn jobs(&self, graph: &ModuleGraph) -> Result<Vec<CompileJob>, SolcError> {
+        let versions: Vec<Version> = self
+            .compilers
+            .iter()
+            .filter_map(|solc| parse_solc_version(&solc.version))
+            .collect();
+
+        let mut groups: BTreeMap<(Version, Option<usize>), Vec<String>> = BTreeMap::new();
+        for component in connected_components(graph) {
+            let settings = self.settings_index(&component[0]);
+            if let Some(other) = component
+                .iter()
+                .find(|id| self.settings_index(id) != settings)
+            {
+                return Err(SolcError::InvalidInput(format!(
+                    "{} and {} are in the same import component but are configured with different settings",
+                    component[0], other
+                )));
+            }
+            let subgraph: ModuleGraph = component
+                .iter()
+                .map(|id| (id.clone(), graph[id].clone()))
+                .collect();
+            let version = select_solc_version(&subgraph, &versions)?;
+            groups.entry((version, settings)).or_default().extend(component);
+        }
+
+        groups
+            .into_iter()
+            .map(|((version, settings), files)| {
+                let solc = self
+                    .compilers
+                    .iter()
+                    .find(|solc| parse_solc_version(&solc.version).as_ref() == Some(&version))
+                    .expect("selected version comes from a compiler")
+                    .clone();
+                let settings = match settings {
+                    Some(i) => Some(self.path_settings[i].1.clone()),
+                    None => self.settings.clone(),
+                };
+                Ok(CompileJob {
+                    solc,
+                    input: input_description(graph, &files, settings),
+                })
+            })
+            .collect()
+    }
+
+    /// Compile every job of `graph` and merge the outputs
+    ///
+    /// If a job fails with diagnostics the remaining jobs still run and the error carries the
+    /// diagnostics of every failed job. Any other error stops the set and kills running jobs.
+    pub async fn compile(&self, graph: &ModuleGraph) -> Result<CompileJobSetOutput, SolcError> {
+        let jobs = self.jobs(graph)?;
+        let mut versions = BTreeMap::new();
+        for job in &jobs {
+            for file in job.files() {
+                versions.insert(file.to_string(), job.solc.version.clone());
+            }
+        }
+
+        let mut pending = jobs.into_iter().enumerate();
+        let mut running = JoinSet::new();
+        let mut outputs = BTreeMap::new();
+        let mut diagnostics = vec![];
+        loop {
+            while running.len() < self.concurrency {
+                let Some((i, job)) = pending.next() else {
+                    break;
+                };
+                running.spawn(async move { (i, job.solc.compile_async(&job.input).await) });
+            }
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let (i, result) =
+                joined.map_err(|e| SolcError::Unknown(format!("Compile job failed: {}", e)))?;
+            match result {
+                Ok(output) => {
+                    outputs.insert(i, output);
+                }
+                Err(SolcError::Diagnostics(entries)) => diagnostics.extend(entries),
+                Err(err) => return Err(err),
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(SolcError::Diagnostics(diagnostics));
+        }
+
+        let mut output = SolcOutput {
+            errors: None,
+            sources: None,
+            contracts: None,
+        };
+        for job_output in outputs.into_values() {
+            output.merge(job_output);
+        }
+        Ok(CompileJobSetOutput { output, versions })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::graph::ModuleInfo;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    /// A solc that reports `file` as source 0 with one contract named after its version
+    fn fake_solc(dir: &Path, version: &str, file: &str) -> Solc {
+        let path = dir.join(format!("solc-{}", version));
+        let script = format!(
+            "#!/bin/sh\ncat > /dev/null\necho '{{\"sources\":{{\"{file}\":{{\"id\":0}}}},\"contracts\":{{\"{file}\":{{\"V{}\":{{}}}}}}}}'\n",
+            version.replace('.', "_")
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Solc::new(path, version.to_string())
+    }
+
+    fn graph() -> ModuleGraph {
+        let module = |code: &str, imports: &[&str]| ModuleInfo {
+            code: code.to_string(),
+            raw_code: code.to_string(),
+            imported_ids: imports.iter().map(PathBuf::from).collect(),
+        };
+        [
+            ("/src/Old.sol", module("pragma solidity ^0.7.0; contract Old {}", &[])),
+            ("/src/A.sol", module("pragma solidity ^0.8.0; import './Lib.sol';", &["/src/Lib.sol"])),
+            ("/src/Lib.sol", module("pragma solidity >=0.7.0; library Lib {}", &[])),
+            ("/test/T.sol", module("pragma solidity >=0.7.0; contract T {}", &[])),
+        ]
+        .into_iter()
+        .map(|(id, module)| (id.to_string(), module))
+        .collect()
+    }
+
+    #[test]
+    fn test_groups_by_version_and_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let optimized = SolcSettings::builder().optimizer(10_000).build();
+        let jobs = CompileJobSet::new([
+            fake_solc(dir.path(), "0.7.6", "/src/Old.sol"),
+            fake_solc(dir.path(), "0.8.20", "/src/A.sol"),
+        ])
+        .with_settings_for("/src/", optimized)
+        .jobs(&graph())
+        .unwrap();
+
+        let planned: Vec<(&str, Vec<&str>, bool)> = jobs
+            .iter()
+            .map(|job| {
+                let optimized = job.input.settings.is_some();
+                (job.solc.version.as_str(), job.files(), optimized)
+            })
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                ("0.7.6", vec!["/src/Old.sol"], true),
+                ("0.8.20", vec!["/test/T.sol"], false),
+                ("0.8.20", vec!["/src/A.sol", "/src/Lib.sol"], true),
+            ]
+        );
+
+        // Files of one import component cannot be split across settings
+        let split = CompileJobSet::new([fake_solc(dir.path(), "0.8.20", "/src/A.sol")])
+            .with_settings_for("/src/Lib.sol", SolcSettings::default())
+            .jobs(&graph());
+        assert!(matches!(split, Err(SolcError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_compiles_jobs_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let set = CompileJobSet::new([
+            fake_solc(dir.path(), "0.7.6", "/src/Old.sol"),
+            fake_solc(dir.path(), "0.8.20", "/src/A.sol"),
+        ])
+        .with_concurrency(2);
+
+        let compiled = set.compile(&graph()).await.unwrap();
+        let contracts = compiled.output.contracts.unwrap();
+        assert!(contracts["/src/Old.sol"].contains_key("V0_7_6"));
+        assert!(contracts["/src/A.sol"].contains_key("V0_8_20"));
+        assert_eq!(compiled.versions["/src/Old.sol"], "0.7.6");
+        assert_eq!(compiled.versions["/src/Lib.sol"], "0.8.20");
+        assert_eq!(compiled.versions["/test/T.sol"], "0.8.20");
+        let sources = compiled.output.sources.unwrap();
+        assert_ne!(sources["/src/Old.sol"].id, sources["/src/A.sol"].id);
+
+        let only_old = CompileJobSet::new([fake_solc(dir.path(), "0.7.6", "/src/Old.sol")]);
+        assert!(matches!(
+            only_old.compile(&graph()).await,
+            Err(SolcError::IncompatiblePragmas(_))
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::error::SolcError;
+use crate::models::{
+    SolcInputDescription, SolcInputSource, SolcLanguage, SolcOutput, SolcSettings,
+};
+use std::collections::HashMap;
+
+impl SolcInputDescription {
+    /// Check that sources and settings fit `language` before handing the input to solc
+    ///
+    /// - `Solidity` sources need `content` or `urls`
+    /// - `Yul` takes exactly one source with `content` or `urls`, and no Solidity-only
+    ///   settings (`remappings`, `stopAfter`, `viaIR`, `modelChecker`)
+    /// - `SolidityAST` sources need an `ast` with a `SourceUnit` root and nothing else, and
+    ///   cannot stop after parsing
+    pub fn check_language(&self) -> Result<(), SolcError> {
+        let invalid = |message: String| Err(SolcError::InvalidInput(message));
+        for (name, source) in &self.sources {
+            let has_text = source.content.is_some() || source.urls.is_some();
+            match self.language {
+                SolcLanguage::Solidity | SolcLanguage::Yul if source.ast.is_some() => {
+                    return invalid(format!(
+                        "Source {} has an AST, which only SolidityAST input accepts",
+                        name
+                    ));
+                }
+                SolcLanguage::Solidity | SolcLanguage::Yul if !has_text => {
+                    return invalid(format!("Source {} has neither content nor urls", name));
+                }
+                SolcLanguage::SolidityAST if has_text => {
+                    return invalid(format!(
+                        "Source {} has content or urls, but SolidityAST input takes only an AST",
+                        name
+                    ));
+                }
+                SolcLanguage::SolidityAST => {
+                    let node_type = source
+                        .ast
+                        .as_ref()
+                        .and_then(|ast| ast.get("nodeType"))
+                        .and_then(serde_json::Value::as_str);
+                    if node_type != Some("SourceUnit") {
+                        return invalid(format!("Source {} needs a SourceUnit AST", name));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let settings = self.settings.as_ref();
+        if let Some(details) = settings
+            .and_then(|settings| settings.optimizer.as_ref())
+            .and_then(|optimizer| optimizer.details.as_ref())
+        {
+            let steps = details
+                .yul_details
+                .as_ref()
+                .and_then(|yul_details| yul_details.optimizer_steps.as_deref());
+            if details.yul == Some(false) && steps.is_some_and(|steps| !steps.trim().is_empty()) {
+                return invalid(
+                    "optimizer.details.yulDetails.optimizerSteps requires optimizer.details.yul"
+                        .to_string(),
+                );
+            }
+        }
+
+        match self.language {
+            SolcLanguage::Yul => {
+                if self.sources.len() != 1 {
+                    return invalid(format!(
+                        "Yul input takes exactly one source, got {}",
+                        self.sources.len()
+                    ));
+                }
+                if let Some(settings) = settings {
+                    let solidity_only = [
+                        ("remappings", settings.remappings.is_some()),
+                        ("stopAfter", settings.stop_after.is_some()),
+                        ("viaIR", settings.via_ir.is_some()),
+                        ("modelChecker", settings.model_checker.is_some()),
+                    ];
+                    if let Some((field, _)) = solidity_only.iter().find(|(_, set)| *set) {
+                        return invalid(format!("settings.{} cannot be used with Yul input", field));
+                    }
+                }
+            }
+            SolcLanguage::SolidityAST => {
+                if settings.is_some_and(|settings| settings.stop_after.is_some()) {
+                    return invalid(
+                        "settings.stopAfter cannot be used with SolidityAST input".to_string(),
+                    );
+                }
+            }
+            SolcLanguage::Solidity => {}
+        }
+        Ok(())
+    }
+
+    /// `SolidityAST` input re-importing every AST of an earlier compilation
+    ///
+    /// The earlier compilation must have selected the `ast` output.
+    pub fn from_asts(output: &SolcOutput, settings: Option<SolcSettings>) -> Result<Self, SolcError> {
+        let sources: HashMap<String, SolcInputSource> = output
+            .sources
+            .iter()
+            .flatten()
+            .filter_map(|(name, source)| {
+                let ast = source.ast.clone()?;
+                Some((
+                    name.clone(),
+                    SolcInputSource {
+                        keccak256: None,
+                        urls: None,
+                        content: None,
+                        ast: Some(ast),
+                    },
+                ))
+            })
+            .collect();
+        if sources.is_empty() {
+            return Err(SolcError::InvalidInput(
+                "Output has no ASTs, select the ast output to re-import it".to_string(),
+            ));
+        }
+        Ok(SolcInputDescription {
+            language: SolcLanguage::SolidityAST,
+            sources,
+            settings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SolcOptimizer, SolcOptimizerDetails, SolcSourceEntry, SolcYulDetails};
+    use serde_json::json;
+
+    #[test]
+    fn test_checks_yul_input() {
+        let yul = SolcInputDescription::builder()
+            .language(SolcLanguage::Yul)
+            .source("helpers.yul", "object \"A\" { code { sstore(0, 1) } }");
+        assert!(yul.clone().build().check_language().is_ok());
+
+        let two_sources = yul.clone().source("more.yul", "{}").build();
+        assert!(matches!(two_sources.check_language(), Err(SolcError::InvalidInput(_))));
+
+        let remapped = yul
+            .clone()
+            .settings(SolcSettings::builder().remapping("a=b").build())
+            .build();
+        assert_eq!(
+            remapped.check_language().unwrap_err().to_string(),
+            "Invalid input: settings.remappings cannot be used with Yul input"
+        );
+
+        let steps_without_yul = yul
+            .settings(SolcSettings {
+                optimizer: Some(SolcOptimizer {
+                    enabled: Some(true),
+                    runs: 200,
+                    details: Some(SolcOptimizerDetails {
+                        yul: Some(false),
+                        yul_details: Some(SolcYulDetails {
+                            stack_allocation: None,
+                            optimizer_steps: Some("dhfoDgvulfnTUtnIf".to_string()),
+                        }),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            })
+            .build();
+        assert!(steps_without_yul.check_language().is_err());
+    }
+
+    #[test]
+    fn test_reimports_asts() {
+        let mut sources = HashMap::new();
+        sources.insert(
+            "A.sol".to_string(),
+            SolcSourceEntry {
+                id: 0,
+                ast: Some(json!({ "nodeType": "SourceUnit", "absolutePath": "A.sol", "nodes": [] })),
+            },
+        );
+        sources.insert("B.sol".to_string(), SolcSourceEntry { id: 1, ast: None });
+        let output = SolcOutput {
+            errors: None,
+            sources: Some(sources),
+            contracts: None,
+        };
+
+        let input = SolcInputDescription::from_asts(&output, None).unwrap();
+        assert_eq!(input.language, SolcLanguage::SolidityAST);
+        assert_eq!(input.sources.len(), 1);
+        assert!(input.check_language().is_ok());
+        assert_eq!(
+            serde_json::to_value(&input).unwrap()["sources"]["A.sol"]["ast"]["nodeType"],
+            "SourceUnit"
+        );
+
+        // Solidity sources cannot carry an AST and AST sources cannot carry content
+        let mut mixed = input.clone();
+        mixed.language = SolcLanguage::Solidity;
+        assert!(mixed.check_language().is_err());
+        let mut with_content = input;
+        with_content.sources.get_mut("A.sol").unwrap().content = Some("contract A {}".to_string());
+        assert!(with_content.check_language().is_err());
+    }
+}
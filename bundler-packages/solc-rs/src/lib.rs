@@ -1,6 +1,29 @@
 pub mod models;
+pub mod builder;
 pub mod solc;
 pub mod error;
+pub mod link;
+pub mod immutables;
+pub mod source_map;
+pub mod disassembler;
+pub mod bytecode_metadata;
+pub mod metadata;
+pub mod storage_layout;
+pub mod gas;
+pub mod model_checker;
+pub mod language;
+pub mod validate;
+pub mod loader;
+pub mod cache;
+pub mod graph;
+pub mod pragma;
+pub mod incremental;
+pub mod jobs;
+#[cfg(feature = "ast")]
+pub mod ast;
+pub mod evm_version;
+pub mod diagnostic;
+pub mod store;
 pub mod napi;
 #[cfg(feature = "examples")]
 pub mod examples;
@@ -9,6 +32,50 @@ pub mod examples;
 pub use models::{
     SolcInputDescription, SolcOutput, SolcLanguage, SolcInputSource, SolcInputSources,
     SolcSettings, SolcOptimizer, SolcOutputSelection, SolcErrorEntry, SolcContractOutput,
+    SolcSeverity, SolcErrorType, SolcErrorComponent, SolcStorageLayout, SolcStorageLayoutItem,
+    SolcStorageType, SolcStorageEncoding, SolcGasEstimates, GasEstimate,
 };
-pub use solc::{Solc, solc_compile};
+pub use builder::{
+    bundler_output_selection, bundler_sync_output_selection, OutputSelectionBuilder,
+    OutputSelectionItem, OutputSelectionPreset, SolcInputDescriptionBuilder, SolcSettingsBuilder,
+};
+pub use source_map::{
+    instruction_pcs, source_files_by_id, Jump, PcSourceLocation, PcSourceMapper, SourceMap,
+    SourceMapEntry,
+};
+pub use disassembler::{
+    disassemble, disassemble_object, metadata_trailer, split_creation_code, CreationCodeSplit,
+    Disassembly, Instruction, Opcode,
+};
+pub use bytecode_metadata::{strip_bytecode_metadata, BytecodeMetadata};
+pub use metadata::{
+    SolcMetadata, SolcMetadataCompiler, SolcMetadataCompilerSettings, SolcMetadataOutput,
+    SolcMetadataSource,
+};
+pub use storage_layout::StorageSlot;
+pub use gas::{function_selector, gas_diff, GasDiff, GasEstimateKey};
+pub use model_checker::{
+    ModelCheckerEngine, ModelCheckerReport, ModelCheckerResult, ModelCheckerStatus,
+    ModelCheckerTarget, ModelCheckerTargetResults,
+};
+pub use validate::{validate, SettingsIssue};
+pub use loader::{resolve_sources, BaseDirLoader, FileUrlLoader, MemoryLoader, SourceLoader};
+pub use cache::{CacheKey, CacheUsage, CompileCache, DiskCache};
+pub use graph::{connected_components, input_description, ModuleGraph, ModuleInfo};
+pub use incremental::IncrementalCompiler;
+pub use jobs::{CompileJob, CompileJobSet, CompileJobSetOutput};
+pub use pragma::{
+    graph_version_req, pragma_expressions, select_solc_version, source_version_req,
+    SolidityVersionReq,
+};
+pub use solc::{Solc, solc_compile, solc_compile_async};
 pub use error::SolcError;
+pub use immutables::{immutable_declarations, resolve_immutable_values, ImmutableDeclaration};
+pub use link::{
+    legacy_library_placeholder, library_placeholder, SolcLinkReferences, UnlinkedLibrary,
+};
+pub use evm_version::EvmVersion;
+pub use diagnostic::{render_diagnostic, render_diagnostics, LineColumn, LineIndex, SourceSpan};
+pub use store::{
+    create_solc, LocalDirInstaller, SolcBuildInfo, SolcBuildList, SolcInstaller, SolcStore,
+};
@@ -0,0 +1,270 @@
+use crate::error::SolcError;
+use crate::models::{
+    SolcBytecodeOutput, SolcContractOutput, SolcDeployedBytecodeOutput, SolcLinkReference,
+};
+use alloy_primitives::{keccak256, Address};
+use std::collections::HashMap;
+
+/// Link references as solc reports them: file -> library -> offsets
+pub type SolcLinkReferences = HashMap<String, HashMap<String, Vec<SolcLinkReference>>>;
+
+/// A library that is still referenced by a placeholder after linking
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnlinkedLibrary {
+    pub file: String,
+    pub library: String,
+    pub references: Vec<SolcLinkReference>,
+}
+
+impl UnlinkedLibrary {
+    /// `file:library`, the name solc uses to derive the placeholder
+    pub fn fully_qualified_name(&self) -> String {
+        format!("{}:{}", self.file, self.library)
+    }
+}
+
+/// The `__$<hash>$__` placeholder solc emits for a fully qualified library name
+pub fn library_placeholder(fully_qualified_name: &str) -> String {
+    let hash = hex::encode(keccak256(fully_qualified_name.as_bytes()));
+    format!("__${}$__", &hash[..34])
+}
+
+/// The `__<name>__` placeholder solc before 0.5 emits, the fully qualified name cut to 36
+/// characters and padded with underscores to 40
+pub fn legacy_library_placeholder(fully_qualified_name: &str) -> String {
+    let name: String = fully_qualified_name.chars().take(36).collect();
+    format!("__{:_<38}", name)
+}
+
+/// Whether `placeholder` is either placeholder form solc uses for `file:library`
+fn is_library_placeholder(placeholder: &str, file: &str, library: &str) -> bool {
+    let fully_qualified_name = format!("{}:{}", file, library);
+    placeholder == library_placeholder(&fully_qualified_name)
+        || placeholder == legacy_library_placeholder(&fully_qualified_name)
+}
+
+impl SolcBytecodeOutput {
+    /// Replace library placeholders in `object` with addresses from `libraries`
+    ///
+    /// `libraries` is keyed by either the fully qualified `file:Library` name or the bare
+    /// library name. Linked references are removed from `link_references`; the ones left are
+    /// returned.
+    pub fn link(
+        &mut self,
+        libraries: &HashMap<String, Address>,
+    ) -> Result<Vec<UnlinkedLibrary>, SolcError> {
+        link_object(&mut self.object, &mut self.link_references, libraries)
+    }
+}
+
+impl SolcDeployedBytecodeOutput {
+    /// Replace library placeholders in `object` with addresses from `libraries`
+    ///
+    /// See [`SolcBytecodeOutput::link`].
+    pub fn link(
+        &mut self,
+        libraries: &HashMap<String, Address>,
+    ) -> Result<Vec<UnlinkedLibrary>, SolcError> {
+        link_object(&mut self.object, &mut self.link_references, libraries)
+    }
+}
+
+impl SolcContractOutput {
+    /// Link both the creation and the deployed bytecode
+    ///
+    /// Returns the libraries left unlinked in either of them.
+    pub fn link(
+        &mut self,
+        libraries: &HashMap<String, Address>,
+    ) -> Result<Vec<UnlinkedLibrary>, SolcError> {
+        let mut unlinked = vec![];
+        if let Some(evm) = self.evm.as_mut() {
+            if let Some(bytecode) = evm.bytecode.as_mut() {
+                unlinked.extend(bytecode.link(libraries)?);
+            }
+            if let Some(deployed_bytecode) = evm.deployed_bytecode.as_mut() {
+                for library in deployed_bytecode.link(libraries)? {
+                    if !unlinked.iter().any(|existing: &UnlinkedLibrary| {
+                        existing.file == library.file && existing.library == library.library
+                    }) {
+                        unlinked.push(library);
+                    }
+                }
+            }
+        }
+        Ok(unlinked)
+    }
+}
+
+fn link_object(
+    object: &mut Option<String>,
+    link_references: &mut Option<SolcLinkReferences>,
+    libraries: &HashMap<String, Address>,
+) -> Result<Vec<UnlinkedLibrary>, SolcError> {
+    let references = match link_references.as_mut() {
+        Some(references) => references,
+        None => return Ok(vec![]),
+    };
+    let object = object
+        .as_mut()
+        .ok_or_else(|| SolcError::LinkError("Bytecode has link references but no object".to_string()))?;
+    let prefix = if object.starts_with("0x") { 2 } else { 0 };
+
+    let mut replacements = vec![];
+    let mut remaining = vec![];
+    for (file, file_references) in references.iter() {
+        for (library, offsets) in file_references {
+            let address = libraries
+                .get(&format!("{}:{}", file, library))
+                .or_else(|| libraries.get(library));
+            let address = match address {
+                Some(address) => hex::encode(address),
+                None => {
+                    remaining.push(UnlinkedLibrary {
+                        file: file.clone(),
+                        library: library.clone(),
+                        references: offsets.clone(),
+                    });
+                    continue;
+                }
+            };
+            // Validate every offset before touching the object so a bad one cannot leave it half linked
+            for offset in offsets {
+                let start = prefix + offset.start as usize * 2;
+                let end = start + offset.length as usize * 2;
+                let is_placeholder = object
+                    .get(start..end)
+                    .is_some_and(|placeholder| is_library_placeholder(placeholder, file, library));
+                if !is_placeholder || end - start != address.len() {
+                    return Err(SolcError::LinkError(format!(
+                        "No placeholder for {}:{} at byte {}",
+                        file, library, offset.start
+                    )));
+                }
+                replacements.push((start..end, address.clone()));
+            }
+        }
+    }
+
+    for (range, address) in replacements {
+        object.replace_range(range, &address);
+    }
+    for (file, file_references) in references.iter_mut() {
+        file_references.retain(|library, _| {
+            remaining
+                .iter()
+                .any(|unlinked| &unlinked.file == file && &unlinked.library == library)
+        });
+    }
+    references.retain(|_, file_references| !file_references.is_empty());
+
+    remaining.sort_by(|a, b| (&a.file, &a.library).cmp(&(&b.file, &b.library)));
+    Ok(remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytecode_with_placeholders() -> SolcBytecodeOutput {
+        let math = library_placeholder("lib/Math.sol:Math");
+        let strings = library_placeholder("lib/Strings.sol:Strings");
+        let object = format!("6080{}00{}5b{}", math, strings, math);
+        let mut math_references = HashMap::new();
+        math_references.insert(
+            "Math".to_string(),
+            vec![
+                SolcLinkReference { start: 2, length: 20 },
+                SolcLinkReference { start: 44, length: 20 },
+            ],
+        );
+        let mut strings_references = HashMap::new();
+        strings_references.insert(
+            "Strings".to_string(),
+            vec![SolcLinkReference { start: 23, length: 20 }],
+        );
+        let mut link_references = HashMap::new();
+        link_references.insert("lib/Math.sol".to_string(), math_references);
+        link_references.insert("lib/Strings.sol".to_string(), strings_references);
+
+        SolcBytecodeOutput {
+            function_debug_data: None,
+            object: Some(object),
+            opcodes: None,
+            source_map: None,
+            generated_sources: None,
+            link_references: Some(link_references),
+        }
+    }
+
+    #[test]
+    fn test_placeholder_format() {
+        let placeholder = library_placeholder("lib/Math.sol:Math");
+        assert_eq!(placeholder.len(), 40);
+        assert!(placeholder.starts_with("__$") && placeholder.ends_with("$__"));
+
+        let legacy = legacy_library_placeholder("Math.sol:Math");
+        assert_eq!(legacy, format!("__Math.sol:Math{}", "_".repeat(25)));
+        assert_eq!(legacy.len(), 40);
+        let long = legacy_library_placeholder("contracts/libraries/SafeMath.sol:SafeMath");
+        assert_eq!(long, "__contracts/libraries/SafeMath.sol:Saf__");
+    }
+
+    #[test]
+    fn test_links_legacy_placeholders() {
+        let placeholder = legacy_library_placeholder("lib/Math.sol:Math");
+        let mut bytecode = bytecode_with_placeholders();
+        bytecode.object = Some(format!("6080{}00{}5b{}", placeholder, placeholder, placeholder));
+        bytecode.link_references.as_mut().unwrap().remove("lib/Strings.sol");
+        let mut libraries = HashMap::new();
+        libraries.insert("Math".to_string(), Address::repeat_byte(0x11));
+
+        assert!(bytecode.link(&libraries).unwrap().is_empty());
+        assert_eq!(&bytecode.object.unwrap()[4..44], "11".repeat(20));
+    }
+
+    #[test]
+    fn test_link_reports_unlinked_libraries() {
+        let mut bytecode = bytecode_with_placeholders();
+        let math: Address = "0x1111111111111111111111111111111111111111".parse().unwrap();
+        let mut libraries = HashMap::new();
+        libraries.insert("lib/Math.sol:Math".to_string(), math);
+
+        let unlinked = bytecode.link(&libraries).unwrap();
+        assert_eq!(unlinked.len(), 1);
+        assert_eq!(unlinked[0].fully_qualified_name(), "lib/Strings.sol:Strings");
+
+        let object = bytecode.object.as_ref().unwrap();
+        assert_eq!(&object[..4], "6080");
+        assert_eq!(&object[4..44], "1111111111111111111111111111111111111111");
+        assert_eq!(&object[88..128], "1111111111111111111111111111111111111111");
+        assert_eq!(&object[46..86], library_placeholder("lib/Strings.sol:Strings"));
+        assert!(!bytecode.link_references.as_ref().unwrap().contains_key("lib/Math.sol"));
+
+        // Bare library names work too
+        let strings: Address = "0x2222222222222222222222222222222222222222".parse().unwrap();
+        let mut libraries = HashMap::new();
+        libraries.insert("Strings".to_string(), strings);
+        assert!(bytecode.link(&libraries).unwrap().is_empty());
+        assert!(!bytecode.object.as_ref().unwrap().contains("__$"));
+        assert!(bytecode.link_references.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_link_rejects_bad_offsets() {
+        let mut bytecode = bytecode_with_placeholders();
+        bytecode.object = Some("6080".to_string());
+        let mut libraries = HashMap::new();
+        libraries.insert("Math".to_string(), Address::ZERO);
+        assert!(matches!(bytecode.link(&libraries), Err(SolcError::LinkError(_))));
+
+        // Anything else starting with `__`, or another library's placeholder, is not a match
+        for other in [format!("__{}", "ab".repeat(19)), library_placeholder("lib/Strings.sol:Strings")] {
+            let mut bytecode = bytecode_with_placeholders();
+            let object = bytecode.object.as_mut().unwrap();
+            object.replace_range(4..44, &other);
+            assert!(matches!(bytecode.link(&libraries), Err(SolcError::LinkError(_))));
+            assert_eq!(&bytecode.object.unwrap()[4..44], other);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use crate::error::SolcError;
+use crate::models::SolcInputDescription;
+use alloy_primitives::keccak256;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Loads the content behind a `SolcInputSource::urls` entry
+pub trait SourceLoader: Send + Sync + Debug {
+    /// Content at `url`, or `None` if this loader does not handle it
+    fn load(&self, url: &str) -> Result<Option<String>, SolcError>;
+}
+
+/// Loads `file://` URLs that point inside one of the allowed directories
+#[derive(Debug, Clone)]
+pub struct FileUrlLoader {
+    allowed_dirs: Vec<PathBuf>,
+}
+
+impl FileUrlLoader {
+    pub fn new(allowed_dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        FileUrlLoader {
+            allowed_dirs: allowed_dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl SourceLoader for FileUrlLoader {
+    fn load(&self, url: &str) -> Result<Option<String>, SolcError> {
+        match url.strip_prefix("file://") {
+            Some(path) => {
+                let path = percent_decode(path).ok_or_else(|| {
+                    SolcError::SourceLoadError(format!("Invalid percent-encoding in {}", url))
+                })?;
+                read_allowed(Path::new(&path), &self.allowed_dirs)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Loads plain paths relative to a list of base directories, first match wins
+///
+/// Paths that resolve outside every base directory, e.g. through `..` or a symlink, are
+/// not read.
+#[derive(Debug, Clone)]
+pub struct BaseDirLoader {
+    base_dirs: Vec<PathBuf>,
+}
+
+impl BaseDirLoader {
+    pub fn new(base_dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        BaseDirLoader {
+            base_dirs: base_dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl SourceLoader for BaseDirLoader {
+    fn load(&self, url: &str) -> Result<Option<String>, SolcError> {
+        if url.contains("://") {
+            return Ok(None);
+        }
+        for base_dir in &self.base_dirs {
+            if let Some(content) = read_allowed(&base_dir.join(url), std::slice::from_ref(base_dir))? {
+                return Ok(Some(content));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Serves sources registered in memory, mostly for tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    sources: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `content` under `url`; clones of this loader see it too
+    pub fn insert(&self, url: impl Into<String>, content: impl Into<String>) {
+        self.sources
+            .write()
+            .expect("memory loader lock poisoned")
+            .insert(url.into(), content.into());
+    }
+}
+
+impl SourceLoader for MemoryLoader {
+    fn load(&self, url: &str) -> Result<Option<String>, SolcError> {
+        Ok(self
+            .sources
+            .read()
+            .expect("memory loader lock poisoned")
+            .get(url)
+            .cloned())
+    }
+}
+
+/// Inline the content of every source that only has `urls`
+///
+/// Each url is tried against each loader in order, like solc does. Loaded content that does
+/// not match the source's `keccak256`, if set, is skipped in favor of the next url; the
+/// first content that matches replaces the source's `urls`. Returns the input unchanged if
+/// no source needs loading.
+pub fn resolve_sources<'a>(
+    input: &'a SolcInputDescription,
+    loaders: &[Arc<dyn SourceLoader>],
+) -> Result<Cow<'a, SolcInputDescription>, SolcError> {
+    let needs_loading = |source: &crate::models::SolcInputSource| {
+        source.content.is_none() && source.ast.is_none() && source.urls.is_some()
+    };
+    if !input.sources.values().any(needs_loading) {
+        return Ok(Cow::Borrowed(input));
+    }
+
+    let mut resolved = input.clone();
+    for (name, source) in resolved.sources.iter_mut() {
+        if !needs_loading(source) {
+            continue;
+        }
+        let urls = source.urls.as_deref().unwrap_or_default();
+        let mut mismatch = None;
+        let mut found = None;
+        'urls: for url in urls {
+            for loader in loaders {
+                let Some(content) = loader.load(url)? else {
+                    continue;
+                };
+                if let Some(expected) = &source.keccak256 {
+                    let actual = hex::encode(keccak256(content.as_bytes()));
+                    let expected_hex = expected.strip_prefix("0x").unwrap_or(expected);
+                    if !expected_hex.eq_ignore_ascii_case(&actual) {
+                        mismatch = Some(format!("0x{}", actual));
+                        continue;
+                    }
+                }
+                found = Some(content);
+                break 'urls;
+            }
+        }
+        let content = match (found, mismatch) {
+            (Some(content), _) => content,
+            (None, Some(actual)) => {
+                return Err(SolcError::SourceHashMismatch {
+                    file: name.clone(),
+                    expected: source.keccak256.clone().unwrap_or_default(),
+                    actual,
+                })
+            }
+            (None, None) => {
+                return Err(SolcError::SourceLoadError(format!(
+                    "No loader could load {} from {}",
+                    name,
+                    urls.join(", ")
+                )))
+            }
+        };
+        source.content = Some(content);
+        source.urls = None;
+    }
+    Ok(Cow::Owned(resolved))
+}
+
+/// Decode `%XX` escapes, `None` if an escape is malformed or the result is not UTF-8
+fn percent_decode(input: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Read `path` if it exists and resolves inside one of `allowed_dirs`
+fn read_allowed(path: &Path, allowed_dirs: &[PathBuf]) -> Result<Option<String>, SolcError> {
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+    let allowed = allowed_dirs
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| path.starts_with(dir));
+    if !allowed || !path.is_file() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(&path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SolcLanguage;
+
+    fn url_input(name: &str, url: &str, keccak: Option<String>) -> SolcInputDescription {
+        let mut input = SolcInputDescription::builder()
+            .language(SolcLanguage::Solidity)
+            .source_urls(name, vec!["bzz-raw://unused".to_string(), url.to_string()])
+            .source("Inline.sol", "contract Inline {}")
+            .build();
+        input.sources.get_mut(name).unwrap().keccak256 = keccak;
+        input
+    }
+
+    #[test]
+    fn test_inlines_and_verifies_content() {
+        let content = "contract A {}";
+        let loader = MemoryLoader::new();
+        loader.insert("mem://A.sol", content);
+        let loaders: Vec<Arc<dyn SourceLoader>> = vec![Arc::new(loader)];
+
+        let keccak = format!("0x{}", hex::encode(keccak256(content.as_bytes())));
+        let input = url_input("A.sol", "mem://A.sol", Some(keccak.clone()));
+        let resolved = resolve_sources(&input, &loaders).unwrap();
+        assert_eq!(resolved.sources["A.sol"].content.as_deref(), Some(content));
+        assert!(resolved.sources["A.sol"].urls.is_none());
+
+        let tampered = url_input("A.sol", "mem://A.sol", Some(format!("0x{}", "00".repeat(32))));
+        assert!(matches!(
+            resolve_sources(&tampered, &loaders),
+            Err(SolcError::SourceHashMismatch { .. })
+        ));
+
+        // Content that does not match falls through to the next url
+        let mirror = MemoryLoader::new();
+        mirror.insert("mem://stale/A.sol", "contract A { uint x; }");
+        mirror.insert("mem://A.sol", content);
+        let mirrors: Vec<Arc<dyn SourceLoader>> = vec![Arc::new(mirror)];
+        let mut fallback = url_input("A.sol", "mem://A.sol", Some(keccak.clone()));
+        fallback.sources.get_mut("A.sol").unwrap().urls =
+            Some(vec!["mem://stale/A.sol".to_string(), "mem://A.sol".to_string()]);
+        let resolved = resolve_sources(&fallback, &mirrors).unwrap();
+        assert_eq!(resolved.sources["A.sol"].content.as_deref(), Some(content));
+
+        let missing = url_input("B.sol", "mem://B.sol", None);
+        assert!(matches!(
+            resolve_sources(&missing, &loaders),
+            Err(SolcError::SourceLoadError(_))
+        ));
+
+        let inline = SolcInputDescription::builder().source("A.sol", content).build();
+        assert!(matches!(resolve_sources(&inline, &[]).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_file_loaders_stay_inside_allowed_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("src");
+        fs::create_dir_all(base.join("lib")).unwrap();
+        fs::write(base.join("lib/Math.sol"), "library Math {}").unwrap();
+        fs::write(base.join("lib/Safe Math.sol"), "library SafeMath {}").unwrap();
+        fs::write(dir.path().join("Secret.sol"), "secret").unwrap();
+
+        let base_loader = BaseDirLoader::new([&base]);
+        assert_eq!(
+            base_loader.load("lib/Math.sol").unwrap().as_deref(),
+            Some("library Math {}")
+        );
+        assert_eq!(base_loader.load("../Secret.sol").unwrap(), None);
+        assert_eq!(base_loader.load("lib/Missing.sol").unwrap(), None);
+
+        let file_loader = FileUrlLoader::new([&base]);
+        let math_url = format!("file://{}", base.join("lib/Math.sol").display());
+        let secret_url = format!("file://{}", dir.path().join("Secret.sol").display());
+        assert!(file_loader.load(&math_url).unwrap().is_some());
+        assert_eq!(file_loader.load(&secret_url).unwrap(), None);
+        assert_eq!(file_loader.load("lib/Math.sol").unwrap(), None);
+        let encoded_url = format!("file://{}/lib/Safe%20Math.sol", base.display());
+        assert_eq!(
+            file_loader.load(&encoded_url).unwrap().as_deref(),
+            Some("library SafeMath {}")
+        );
+        assert!(matches!(
+            file_loader.load("file:///src/%zz.sol"),
+            Err(SolcError::SourceLoadError(_))
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::builder::{OutputSelectionBuilder, OutputSelectionItem};
+use crate::error::SolcError;
+use crate::evm_version::{parse_solc_version, EvmVersion};
+use crate::models::{
+    SolcContractOutput, SolcDebugSettings, SolcInputDescription, SolcInputSource, SolcLanguage,
+    SolcMetadataSettings, SolcModelChecker, SolcOptimizer, SolcSettings,
+};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+// The compiler that produced a contract
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SolcMetadataCompiler {
+    pub version: String,
+    // Only set for compilers built to emscripten
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keccak256: Option<String>,
+}
+
+// Settings recorded in the metadata, a superset of the input settings that affect bytecode
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolcMetadataCompilerSettings {
+    // File name to contract name of the contract this metadata belongs to
+    #[serde(default)]
+    pub compilation_target: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm_version: Option<EvmVersion>,
+    // Fully qualified `file:Library` name to address
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub libraries: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<SolcMetadataSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimizer: Option<SolcOptimizer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remappings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "viaIR")]
+    pub via_ir: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<SolcDebugSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_checker: Option<SolcModelChecker>,
+}
+
+// A source file that contributed to the contract
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SolcMetadataSource {
+    pub keccak256: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    // Only set when compiled with `useLiteralContent`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+// Contract interface and documentation
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SolcMetadataOutput {
+    #[serde(default)]
+    pub abi: serde_json::Value,
+    #[serde(default)]
+    pub devdoc: serde_json::Value,
+    #[serde(default)]
+    pub userdoc: serde_json::Value,
+}
+
+// The metadata JSON solc emits for each contract
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SolcMetadata {
+    pub compiler: SolcMetadataCompiler,
+    pub language: SolcLanguage,
+    pub output: SolcMetadataOutput,
+    pub settings: SolcMetadataCompilerSettings,
+    pub sources: BTreeMap<String, SolcMetadataSource>,
+    pub version: u32,
+}
+
+impl SolcMetadata {
+    pub fn parse(metadata: &str) -> Result<Self, SolcError> {
+        serde_json::from_str(metadata)
+            .map_err(|e| SolcError::SerializationError(format!("Invalid contract metadata: {}", e)))
+    }
+
+    /// Version of the compiler that produced the contract, e.g. `0.8.20+commit.a1b79de6`
+    pub fn compiler_version(&self) -> Option<Version> {
+        parse_solc_version(&self.compiler.version)
+    }
+
+    /// File and name of the contract this metadata describes
+    pub fn compilation_target(&self) -> Option<(&str, &str)> {
+        self.settings
+            .compilation_target
+            .iter()
+            .next()
+            .map(|(file, contract)| (file.as_str(), contract.as_str()))
+    }
+
+    /// Rebuild the standard JSON input that produced this contract
+    ///
+    /// Sources carry their literal content when the metadata has it and `urls` otherwise;
+    /// either way `keccak256` is set so solc rejects modified files. Outputs are selected
+    /// only for the compilation target: abi, metadata and both bytecode objects.
+    pub fn to_input_description(&self) -> SolcInputDescription {
+        let sources = self
+            .sources
+            .iter()
+            .map(|(file, source)| {
+                let input = SolcInputSource {
+                    keccak256: Some(source.keccak256.clone()),
+                    urls: match source.content {
+                        Some(_) => None,
+                        None => Some(source.urls.clone()),
+                    },
+                    content: source.content.clone(),
+                    ast: None,
+                };
+                (file.clone(), input)
+            })
+            .collect();
+
+        let settings = &self.settings;
+        let mut libraries: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (name, address) in &settings.libraries {
+            let (file, library) = name.rsplit_once(':').unwrap_or(("", name));
+            libraries
+                .entry(file.to_string())
+                .or_default()
+                .insert(library.to_string(), address.clone());
+        }
+        let output_selection = self.compilation_target().map(|(file, contract)| {
+            OutputSelectionBuilder::new()
+                .contract(
+                    file,
+                    contract,
+                    [
+                        OutputSelectionItem::Abi,
+                        OutputSelectionItem::Metadata,
+                        OutputSelectionItem::EvmBytecodeObject,
+                        OutputSelectionItem::EvmDeployedBytecodeObject,
+                    ],
+                )
+                .build()
+        });
+
+        SolcInputDescription {
+            language: self.language.clone(),
+            sources,
+            settings: Some(SolcSettings {
+                stop_after: None,
+                remappings: (!settings.remappings.is_empty()).then(|| settings.remappings.clone()),
+                optimizer: settings.optimizer.clone(),
+                evm_version: settings.evm_version,
+                via_ir: settings.via_ir,
+                debug: settings.debug.clone(),
+                metadata: settings.metadata.clone(),
+                libraries: (!libraries.is_empty()).then_some(libraries),
+                output_selection,
+                model_checker: settings.model_checker.clone(),
+            }),
+        }
+    }
+}
+
+impl FromStr for SolcMetadata {
+    type Err = SolcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SolcMetadata::parse(s)
+    }
+}
+
+impl SolcContractOutput {
+    /// Parse `metadata`, which must be selected
+    pub fn parsed_metadata(&self) -> Result<Option<SolcMetadata>, SolcError> {
+        self.metadata.as_deref().map(SolcMetadata::parse).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"{
+        "compiler": { "version": "0.8.20+commit.a1b79de6" },
+        "language": "Solidity",
+        "output": {
+            "abi": [{ "inputs": [], "name": "count", "outputs": [], "stateMutability": "view", "type": "function" }],
+            "devdoc": { "kind": "dev", "methods": {}, "version": 1 },
+            "userdoc": { "kind": "user", "methods": {}, "version": 1 }
+        },
+        "settings": {
+            "compilationTarget": { "src/Counter.sol": "Counter" },
+            "evmVersion": "shanghai",
+            "libraries": { "src/Math.sol:Math": "0x1111111111111111111111111111111111111111" },
+            "metadata": { "bytecodeHash": "ipfs" },
+            "optimizer": { "enabled": true, "runs": 200 },
+            "remappings": ["forge-std/=lib/forge-std/src/"]
+        },
+        "sources": {
+            "src/Counter.sol": {
+                "keccak256": "0x3f5a",
+                "license": "MIT",
+                "urls": ["bzz-raw://abc", "dweb:/ipfs/Qm1"]
+            },
+            "src/Math.sol": {
+                "keccak256": "0x9b1c",
+                "content": "library Math {}",
+                "license": "UNLICENSED"
+            }
+        },
+        "version": 1
+    }"#;
+
+    #[test]
+    fn test_parses_metadata() {
+        let contract = SolcContractOutput {
+            abi: None,
+            metadata: Some(METADATA.to_string()),
+            userdoc: None,
+            devdoc: None,
+            ir: None,
+            storage_layout: None,
+            evm: None,
+            ewasm: None,
+        };
+        let metadata = contract.parsed_metadata().unwrap().unwrap();
+        assert_eq!(metadata.compiler_version(), Some(Version::parse("0.8.20+commit.a1b79de6").unwrap()));
+        assert_eq!(metadata.compilation_target(), Some(("src/Counter.sol", "Counter")));
+        assert_eq!(metadata.settings.evm_version, Some(EvmVersion::Shanghai));
+        assert_eq!(metadata.sources["src/Counter.sol"].license.as_deref(), Some("MIT"));
+        assert_eq!(metadata.output.abi[0]["name"], "count");
+        assert_eq!(metadata.output.devdoc["kind"], "dev");
+
+        assert!(matches!(
+            SolcMetadata::parse("{}"),
+            Err(SolcError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_rebuilds_input_description() {
+        let input = METADATA.parse::<SolcMetadata>().unwrap().to_input_description();
+        assert_eq!(input.language, SolcLanguage::Solidity);
+
+        let counter = &input.sources["src/Counter.sol"];
+        assert_eq!(counter.keccak256.as_deref(), Some("0x3f5a"));
+        assert_eq!(counter.urls.as_ref().unwrap().len(), 2);
+        assert!(counter.content.is_none());
+        let math = &input.sources["src/Math.sol"];
+        assert_eq!(math.content.as_deref(), Some("library Math {}"));
+        assert!(math.urls.is_none());
+
+        let settings = input.settings.unwrap();
+        assert_eq!(settings.optimizer.unwrap().runs, 200);
+        assert_eq!(settings.evm_version, Some(EvmVersion::Shanghai));
+        assert_eq!(
+            settings.libraries.unwrap()["src/Math.sol"]["Math"],
+            "0x1111111111111111111111111111111111111111"
+        );
+        assert_eq!(settings.remappings.unwrap(), vec!["forge-std/=lib/forge-std/src/"]);
+        assert_eq!(
+            settings.output_selection.unwrap()["src/Counter.sol"]["Counter"],
+            vec!["abi", "metadata", "evm.bytecode.object", "evm.deployedBytecode.object"]
+        );
+    }
+
+    #[test]
+    fn test_round_trips_append_cbor() {
+        let metadata = METADATA.replace(
+            r#""metadata": { "bytecodeHash": "ipfs" }"#,
+            r#""metadata": { "appendCBOR": false, "bytecodeHash": "none" }"#,
+        );
+        let metadata = SolcMetadata::parse(&metadata).unwrap();
+        let settings = metadata.settings.metadata.as_ref().unwrap();
+        assert_eq!(settings.append_cbor, Some(false));
+
+        let input = serde_json::to_value(metadata.to_input_description()).unwrap();
+        assert_eq!(
+            input["settings"]["metadata"],
+            serde_json::json!({ "appendCBOR": false, "bytecodeHash": "none" })
+        );
+        let reparsed: SolcMetadata =
+            serde_json::from_value(serde_json::to_value(&metadata).unwrap()).unwrap();
+        assert_eq!(reparsed, metadata);
+    }
+}
@@ -0,0 +1,413 @@
+use crate::models::{SolcErrorEntry, SolcOutput, SolcSourceLocation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A kind of property the model checker verifies, named as in `modelChecker.targets`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelCheckerTarget {
+    ConstantCondition,
+    Underflow,
+    Overflow,
+    DivByZero,
+    Balance,
+    Assert,
+    PopEmptyArray,
+    OutOfBounds,
+}
+
+impl ModelCheckerTarget {
+    pub const ALL: [ModelCheckerTarget; 8] = [
+        ModelCheckerTarget::ConstantCondition,
+        ModelCheckerTarget::Underflow,
+        ModelCheckerTarget::Overflow,
+        ModelCheckerTarget::DivByZero,
+        ModelCheckerTarget::Balance,
+        ModelCheckerTarget::Assert,
+        ModelCheckerTarget::PopEmptyArray,
+        ModelCheckerTarget::OutOfBounds,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelCheckerTarget::ConstantCondition => "constantCondition",
+            ModelCheckerTarget::Underflow => "underflow",
+            ModelCheckerTarget::Overflow => "overflow",
+            ModelCheckerTarget::DivByZero => "divByZero",
+            ModelCheckerTarget::Balance => "balance",
+            ModelCheckerTarget::Assert => "assert",
+            ModelCheckerTarget::PopEmptyArray => "popEmptyArray",
+            ModelCheckerTarget::OutOfBounds => "outOfBounds",
+        }
+    }
+
+    /// The target a model checker message is about, from the wording solc uses for it
+    fn from_message(message: &str) -> Option<Self> {
+        let target = if message.contains("Assertion violation") {
+            ModelCheckerTarget::Assert
+        } else if message.contains("Underflow") {
+            ModelCheckerTarget::Underflow
+        } else if message.contains("Overflow") {
+            ModelCheckerTarget::Overflow
+        } else if message.contains("Division by zero") {
+            ModelCheckerTarget::DivByZero
+        } else if message.contains("Insufficient funds") {
+            ModelCheckerTarget::Balance
+        } else if message.contains("Empty array \"pop\"") {
+            ModelCheckerTarget::PopEmptyArray
+        } else if message.contains("Out of bounds access") {
+            ModelCheckerTarget::OutOfBounds
+        } else if message.contains("Condition is always") {
+            ModelCheckerTarget::ConstantCondition
+        } else {
+            return None;
+        };
+        Some(target)
+    }
+}
+
+impl fmt::Display for ModelCheckerTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What the model checker concluded about a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelCheckerStatus {
+    /// The property holds, reported with `showProved`
+    Proved,
+    /// The property may not hold; the solver could neither prove nor refute it
+    Unproved,
+    /// The property does not hold, usually with a counterexample
+    Violated,
+    /// The model checker could not analyze the code, or the solver failed or timed out
+    Unsupported,
+}
+
+/// The engine that produced a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelCheckerEngine {
+    Chc,
+    Bmc,
+}
+
+/// One model checker finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCheckerResult {
+    pub status: ModelCheckerStatus,
+    pub engine: Option<ModelCheckerEngine>,
+    pub source_location: Option<SolcSourceLocation>,
+    /// The first line of the message, without the counterexample
+    pub message: String,
+    /// Variable values and transaction trace that reach a violation
+    pub counterexample: Option<String>,
+}
+
+/// Results of one contract by target
+pub type ModelCheckerTargetResults = BTreeMap<ModelCheckerTarget, Vec<ModelCheckerResult>>;
+
+/// Model checker results of a compilation
+///
+/// Results about a target in a known contract are kept under the file, contract and
+/// target, like `SolcOutput::contracts`. Everything else, such as unsupported constructs,
+/// missing solvers and "could not be proved" summaries, is kept in `other`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelCheckerReport {
+    pub contracts: BTreeMap<String, BTreeMap<String, ModelCheckerTargetResults>>,
+    pub other: Vec<ModelCheckerResult>,
+}
+
+impl ModelCheckerReport {
+    /// Collect the model checker messages of `output`
+    ///
+    /// Contracts are found from the source ASTs when the `ast` output is selected, and from
+    /// the counterexample's transaction trace otherwise.
+    pub fn from_output(output: &SolcOutput) -> Self {
+        let ranges = contract_ranges(output);
+        Self::collect(output.errors.as_deref().unwrap_or_default(), &ranges)
+    }
+
+    /// Collect model checker messages from diagnostics alone, e.g. those of a failed compilation
+    pub fn from_errors(errors: &[SolcErrorEntry]) -> Self {
+        Self::collect(errors, &[])
+    }
+
+    fn collect(errors: &[SolcErrorEntry], ranges: &[ContractRange]) -> Self {
+        let mut report = ModelCheckerReport::default();
+        for entry in errors {
+            let Some((target, result)) = parse_entry(entry) else {
+                continue;
+            };
+            let location = result.source_location.as_ref();
+            let contract = location
+                .and_then(|location| {
+                    ranges
+                        .iter()
+                        .find(|range| {
+                            range.file == location.file
+                                && range.start <= location.start
+                                && location.start < range.end
+                        })
+                        .map(|range| range.contract.clone())
+                })
+                .or_else(|| result.counterexample.as_deref().and_then(trace_contract));
+            match (target, location, contract) {
+                (Some(target), Some(location), Some(contract)) => report
+                    .contracts
+                    .entry(location.file.clone())
+                    .or_default()
+                    .entry(contract)
+                    .or_default()
+                    .entry(target)
+                    .or_default()
+                    .push(result),
+                _ => report.other.push(result),
+            }
+        }
+        report
+    }
+
+    /// Every result, those in `other` last
+    pub fn results(&self) -> impl Iterator<Item = &ModelCheckerResult> {
+        self.contracts
+            .values()
+            .flat_map(BTreeMap::values)
+            .flat_map(BTreeMap::values)
+            .flatten()
+            .chain(&self.other)
+    }
+
+    pub fn count(&self, status: ModelCheckerStatus) -> usize {
+        self.results().filter(|result| result.status == status).count()
+    }
+
+    /// Whether any property was shown not to hold
+    pub fn has_violations(&self) -> bool {
+        self.count(ModelCheckerStatus::Violated) > 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty() && self.other.is_empty()
+    }
+}
+
+impl SolcOutput {
+    /// The typed model checker results among this output's diagnostics
+    pub fn model_checker_report(&self) -> ModelCheckerReport {
+        ModelCheckerReport::from_output(self)
+    }
+}
+
+/// Target and result of a model checker message, `None` for other diagnostics
+fn parse_entry(entry: &SolcErrorEntry) -> Option<(Option<ModelCheckerTarget>, ModelCheckerResult)> {
+    let (first_line, rest) = entry.message.split_once('\n').unwrap_or((&entry.message, ""));
+    let (engine, text) = if let Some(text) = first_line.strip_prefix("CHC: ") {
+        (Some(ModelCheckerEngine::Chc), text)
+    } else if let Some(text) = first_line.strip_prefix("BMC: ") {
+        (Some(ModelCheckerEngine::Bmc), text)
+    } else if is_unsupported(first_line) {
+        (None, first_line)
+    } else {
+        return None;
+    };
+
+    let status = if is_unsupported(text) {
+        ModelCheckerStatus::Unsupported
+    } else if text.contains("is safe") || text.contains("proved safe") {
+        ModelCheckerStatus::Proved
+    } else if text.contains("happens here") || rest.contains("Counterexample:") {
+        ModelCheckerStatus::Violated
+    } else {
+        // "might happen", "could not be proved" and wording this crate does not know yet
+        ModelCheckerStatus::Unproved
+    };
+    let counterexample = rest
+        .split_once("Counterexample:")
+        .map(|(_, counterexample)| counterexample.trim().to_string())
+        .filter(|counterexample| !counterexample.is_empty());
+    let target = match status {
+        ModelCheckerStatus::Unsupported => None,
+        _ => ModelCheckerTarget::from_message(text),
+    };
+    Some((
+        target,
+        ModelCheckerResult {
+            status,
+            engine,
+            source_location: entry.source_location.clone(),
+            message: first_line.to_string(),
+            counterexample,
+        },
+    ))
+}
+
+/// Whether a message says the code could not be analyzed or the solver gave no answer
+fn is_unsupported(message: &str) -> bool {
+    let lowercase = message.to_lowercase();
+    message.contains("does not yet support")
+        || message.contains("not yet supported")
+        || message.contains("analysis was not possible")
+        || message.contains("Error trying to invoke SMT solver")
+        || message.contains("No SMT solver available")
+        || lowercase.contains("timeout")
+        || lowercase.contains("timed out")
+}
+
+/// The contract of the first call in a counterexample's transaction trace
+fn trace_contract(counterexample: &str) -> Option<String> {
+    let (_, trace) = counterexample.split_once("Transaction trace:")?;
+    let call = trace.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let (contract, _) = call.split_once('.')?;
+    Some(contract.to_string())
+}
+
+/// Byte range of a contract definition within a source file
+struct ContractRange {
+    file: String,
+    contract: String,
+    start: i32,
+    end: i32,
+}
+
+fn contract_ranges(output: &SolcOutput) -> Vec<ContractRange> {
+    let mut ranges = vec![];
+    for (file, source) in output.sources.iter().flatten() {
+        let nodes = source
+            .ast
+            .as_ref()
+            .and_then(|ast| ast.get("nodes"))
+            .and_then(serde_json::Value::as_array);
+        for node in nodes.into_iter().flatten() {
+            if node.get("nodeType").and_then(serde_json::Value::as_str) != Some("ContractDefinition") {
+                continue;
+            }
+            let name = node.get("name").and_then(serde_json::Value::as_str);
+            let src = node.get("src").and_then(serde_json::Value::as_str);
+            let (Some(name), Some(src)) = (name, src) else {
+                continue;
+            };
+            let mut parts = src.split(':').map(str::parse::<i32>);
+            if let (Some(Ok(start)), Some(Ok(length))) = (parts.next(), parts.next()) {
+                ranges.push(ContractRange {
+                    file: file.clone(),
+                    contract: name.to_string(),
+                    start,
+                    end: start + length,
+                });
+            }
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output() -> SolcOutput {
+        serde_json::from_value(json!({
+            "errors": [
+                {
+                    "type": "Warning", "component": "general", "severity": "warning", "errorCode": "6328",
+                    "message": "CHC: Assertion violation happens here.\nCounterexample:\nx = 0\n\nTransaction trace:\nVault.constructor()\nVault.withdraw(0)",
+                    "sourceLocation": { "file": "Vault.sol", "start": 120, "end": 135 }
+                },
+                {
+                    "type": "Warning", "component": "general", "severity": "warning", "errorCode": "4984",
+                    "message": "CHC: Overflow (resulting value larger than 2**256 - 1) might happen here.",
+                    "sourceLocation": { "file": "Vault.sol", "start": 160, "end": 171 }
+                },
+                {
+                    "type": "Info", "component": "general", "severity": "info", "errorCode": "9576",
+                    "message": "CHC: Division by zero check is safe!",
+                    "sourceLocation": { "file": "Vault.sol", "start": 300, "end": 305 }
+                },
+                {
+                    "type": "Warning", "component": "general", "severity": "warning", "errorCode": "7650",
+                    "message": "Assertion checker does not yet support this expression.",
+                    "sourceLocation": { "file": "Vault.sol", "start": 180, "end": 190 }
+                },
+                {
+                    "type": "Warning", "component": "general", "severity": "warning", "errorCode": "2072",
+                    "message": "Unused local variable.",
+                    "sourceLocation": { "file": "Vault.sol", "start": 200, "end": 210 }
+                }
+            ],
+            "sources": {
+                "Vault.sol": {
+                    "id": 0,
+                    "ast": {
+                        "nodeType": "SourceUnit",
+                        "nodes": [
+                            { "nodeType": "PragmaDirective", "src": "0:23:0" },
+                            { "nodeType": "ContractDefinition", "name": "Vault", "src": "25:250:0" },
+                            { "nodeType": "ContractDefinition", "name": "Math", "src": "280:60:0" }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_groups_results_by_contract_and_target() {
+        let report = output().model_checker_report();
+        let vault = &report.contracts["Vault.sol"]["Vault"];
+
+        let violation = &vault[&ModelCheckerTarget::Assert][0];
+        assert_eq!(violation.status, ModelCheckerStatus::Violated);
+        assert_eq!(violation.engine, Some(ModelCheckerEngine::Chc));
+        assert_eq!(violation.message, "CHC: Assertion violation happens here.");
+        assert_eq!(
+            violation.counterexample.as_deref(),
+            Some("x = 0\n\nTransaction trace:\nVault.constructor()\nVault.withdraw(0)")
+        );
+        assert_eq!(vault[&ModelCheckerTarget::Overflow][0].status, ModelCheckerStatus::Unproved);
+
+        let math = &report.contracts["Vault.sol"]["Math"];
+        assert_eq!(math[&ModelCheckerTarget::DivByZero][0].status, ModelCheckerStatus::Proved);
+
+        assert_eq!(report.other.len(), 1);
+        assert_eq!(report.other[0].status, ModelCheckerStatus::Unsupported);
+        assert!(report.has_violations());
+        assert_eq!(report.count(ModelCheckerStatus::Unproved), 1);
+        assert_eq!(report.results().count(), 4);
+    }
+
+    #[test]
+    fn test_falls_back_to_transaction_trace() {
+        let errors = output().errors.unwrap();
+        let report = ModelCheckerReport::from_errors(&errors);
+        assert_eq!(report.contracts["Vault.sol"]["Vault"].len(), 1);
+        // Without an AST or a trace the contract is unknown
+        assert_eq!(report.other.len(), 3);
+        assert_eq!(
+            serde_json::to_value(ModelCheckerTarget::PopEmptyArray).unwrap(),
+            "popEmptyArray"
+        );
+    }
+
+    #[test]
+    fn test_solver_failures_are_not_violations() {
+        let errors: Vec<SolcErrorEntry> = serde_json::from_value(json!([
+            {
+                "type": "Warning", "component": "general", "severity": "warning", "errorCode": "8084",
+                "message": "BMC: Error trying to invoke SMT solver.",
+                "sourceLocation": { "file": "Vault.sol", "start": 120, "end": 135 }
+            },
+            {
+                "type": "Warning", "component": "general", "severity": "warning",
+                "message": "CHC: Assertion violation check stopped after a timeout.",
+                "sourceLocation": { "file": "Vault.sol", "start": 140, "end": 150 }
+            }
+        ]))
+        .unwrap();
+        let report = ModelCheckerReport::from_errors(&errors);
+        assert_eq!(report.count(ModelCheckerStatus::Unsupported), 2);
+        assert!(!report.has_violations());
+    }
+}
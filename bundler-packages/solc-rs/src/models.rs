@@ -1,6 +1,7 @@
 // Type definitions for solc input/output
 // Based on the Solidity documentation at https://docs.soliditylang.org/en/v0.8.20/using-the-compiler.html
 
+use crate::evm_version::EvmVersion;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -24,6 +25,9 @@ pub struct SolcInputSource {
     pub urls: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    // Compact AST of the file, for `SolidityAST` input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ast: Option<serde_json::Value>,
 }
 
 // Map of file names to source content
@@ -115,6 +119,7 @@ pub struct SolcDebugSettings {
 #[serde(rename_all = "camelCase")]
 pub struct SolcMetadataSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "appendCBOR")]
     pub append_cbor: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub use_literal_content: Option<bool>,
@@ -133,7 +138,7 @@ pub struct SolcSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub optimizer: Option<SolcOptimizer>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub evm_version: Option<String>,
+    pub evm_version: Option<EvmVersion>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "viaIR")]
     pub via_ir: Option<bool>,
@@ -167,9 +172,9 @@ pub struct SolcErrorEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secondary_source_locations: Option<Vec<SolcSecondarySourceLocation>>,
     #[serde(rename = "type")]
-    pub error_type: String,
-    pub component: String,
-    pub severity: String,
+    pub error_type: SolcErrorType,
+    pub component: SolcErrorComponent,
+    pub severity: SolcSeverity,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_code: Option<String>,
     pub message: String,
@@ -177,20 +182,231 @@ pub struct SolcErrorEntry {
     pub formatted_message: Option<String>,
 }
 
-// Source location in solc output
+impl SolcErrorEntry {
+    pub fn is_error(&self) -> bool {
+        self.severity.is_error()
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.severity.is_warning()
+    }
+
+    pub fn is_info(&self) -> bool {
+        self.severity.is_info()
+    }
+}
+
+// Severity of an error entry in solc output
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum SolcSeverity {
+    Error,
+    Warning,
+    Info,
+    Unknown(String),
+}
+
+impl SolcSeverity {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SolcSeverity::Error => "error",
+            SolcSeverity::Warning => "warning",
+            SolcSeverity::Info => "info",
+            SolcSeverity::Unknown(value) => value,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, SolcSeverity::Error)
+    }
+
+    pub fn is_warning(&self) -> bool {
+        matches!(self, SolcSeverity::Warning)
+    }
+
+    pub fn is_info(&self) -> bool {
+        matches!(self, SolcSeverity::Info)
+    }
+}
+
+impl From<String> for SolcSeverity {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "error" => SolcSeverity::Error,
+            "warning" => SolcSeverity::Warning,
+            "info" => SolcSeverity::Info,
+            _ => SolcSeverity::Unknown(value),
+        }
+    }
+}
+
+impl From<SolcSeverity> for String {
+    fn from(value: SolcSeverity) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for SolcSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Type of an error entry in solc output
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum SolcErrorType {
+    JSONError,
+    IOError,
+    ParserError,
+    DocstringParsingError,
+    SyntaxError,
+    DeclarationError,
+    TypeError,
+    UnimplementedFeatureError,
+    InternalCompilerError,
+    Exception,
+    CompilerError,
+    FatalError,
+    YulException,
+    SMTLogicException,
+    CodeGenerationError,
+    Warning,
+    Info,
+    Unknown(String),
+}
+
+impl SolcErrorType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SolcErrorType::JSONError => "JSONError",
+            SolcErrorType::IOError => "IOError",
+            SolcErrorType::ParserError => "ParserError",
+            SolcErrorType::DocstringParsingError => "DocstringParsingError",
+            SolcErrorType::SyntaxError => "SyntaxError",
+            SolcErrorType::DeclarationError => "DeclarationError",
+            SolcErrorType::TypeError => "TypeError",
+            SolcErrorType::UnimplementedFeatureError => "UnimplementedFeatureError",
+            SolcErrorType::InternalCompilerError => "InternalCompilerError",
+            SolcErrorType::Exception => "Exception",
+            SolcErrorType::CompilerError => "CompilerError",
+            SolcErrorType::FatalError => "FatalError",
+            SolcErrorType::YulException => "YulException",
+            SolcErrorType::SMTLogicException => "SMTLogicException",
+            SolcErrorType::CodeGenerationError => "CodeGenerationError",
+            SolcErrorType::Warning => "Warning",
+            SolcErrorType::Info => "Info",
+            SolcErrorType::Unknown(value) => value,
+        }
+    }
+
+    /// Errors caused by a compiler bug or limitation rather than the input
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            SolcErrorType::InternalCompilerError
+                | SolcErrorType::Exception
+                | SolcErrorType::CompilerError
+                | SolcErrorType::FatalError
+                | SolcErrorType::YulException
+                | SolcErrorType::SMTLogicException
+                | SolcErrorType::UnimplementedFeatureError
+        )
+    }
+}
+
+impl From<String> for SolcErrorType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "JSONError" => SolcErrorType::JSONError,
+            "IOError" => SolcErrorType::IOError,
+            "ParserError" => SolcErrorType::ParserError,
+            "DocstringParsingError" => SolcErrorType::DocstringParsingError,
+            "SyntaxError" => SolcErrorType::SyntaxError,
+            "DeclarationError" => SolcErrorType::DeclarationError,
+            "TypeError" => SolcErrorType::TypeError,
+            "UnimplementedFeatureError" => SolcErrorType::UnimplementedFeatureError,
+            "InternalCompilerError" => SolcErrorType::InternalCompilerError,
+            "Exception" => SolcErrorType::Exception,
+            "CompilerError" => SolcErrorType::CompilerError,
+            "FatalError" => SolcErrorType::FatalError,
+            "YulException" => SolcErrorType::YulException,
+            "SMTLogicException" => SolcErrorType::SMTLogicException,
+            "CodeGenerationError" => SolcErrorType::CodeGenerationError,
+            "Warning" => SolcErrorType::Warning,
+            "Info" => SolcErrorType::Info,
+            _ => SolcErrorType::Unknown(value),
+        }
+    }
+}
+
+impl From<SolcErrorType> for String {
+    fn from(value: SolcErrorType) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for SolcErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Component an error entry in solc output originated from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum SolcErrorComponent {
+    General,
+    Ewasm,
+    Unknown(String),
+}
+
+impl SolcErrorComponent {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SolcErrorComponent::General => "general",
+            SolcErrorComponent::Ewasm => "ewasm",
+            SolcErrorComponent::Unknown(value) => value,
+        }
+    }
+}
+
+impl From<String> for SolcErrorComponent {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "general" => SolcErrorComponent::General,
+            "ewasm" => SolcErrorComponent::Ewasm,
+            _ => SolcErrorComponent::Unknown(value),
+        }
+    }
+}
+
+impl From<SolcErrorComponent> for String {
+    fn from(value: SolcErrorComponent) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for SolcErrorComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Source location in solc output, `start` and `end` are -1 when there is no range
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SolcSourceLocation {
     pub file: String,
-    pub start: u32,
-    pub end: u32,
+    pub start: i32,
+    pub end: i32,
 }
 
 // Secondary source location in solc output
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SolcSecondarySourceLocation {
     pub file: String,
-    pub start: u32,
-    pub end: u32,
+    pub start: i32,
+    pub end: i32,
     pub message: String,
 }
 
@@ -228,7 +444,40 @@ pub struct SolcContractOutput {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SolcStorageLayout {
     pub storage: Vec<SolcStorageLayoutItem>,
-    pub types: HashMap<String, serde_json::Value>,
+    // solc emits `null` for contracts without state variables
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub types: HashMap<String, SolcStorageType>,
+}
+
+// How a storage type is laid out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SolcStorageEncoding {
+    Inplace,
+    Mapping,
+    DynamicArray,
+    Bytes,
+}
+
+// Type definition referenced by a storage layout item
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolcStorageType {
+    pub encoding: SolcStorageEncoding,
+    pub label: String,
+    pub number_of_bytes: String,
+    // Mapping key type id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    // Mapping value type id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    // Array element type id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    // Struct members, with slots relative to the struct
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<SolcStorageLayoutItem>>,
 }
 
 // Storage layout item in solc output
@@ -342,19 +591,79 @@ pub struct SolcGeneratedSource {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SolcGasEstimates {
     pub creation: SolcGasEstimatesCreation,
+    // Keyed by function signature, `""` for the fallback function
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub external: Option<HashMap<String, String>>,
+    pub external: Option<HashMap<String, GasEstimate>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub internal: Option<HashMap<String, String>>,
+    pub internal: Option<HashMap<String, GasEstimate>>,
 }
 
 // Gas estimates creation data in solc output
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SolcGasEstimatesCreation {
-    pub code_deposit_cost: String,
-    pub execution_cost: String,
-    pub total_cost: String,
+    pub code_deposit_cost: GasEstimate,
+    pub execution_cost: GasEstimate,
+    pub total_cost: GasEstimate,
+}
+
+// A gas estimate, which solc reports as a decimal string or `"infinite"`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum GasEstimate {
+    Finite(u64),
+    // Unbounded, e.g. because of loops or calls into unknown code
+    Infinite,
+}
+
+impl GasEstimate {
+    pub fn as_finite(&self) -> Option<u64> {
+        match self {
+            GasEstimate::Finite(gas) => Some(*gas),
+            GasEstimate::Infinite => None,
+        }
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        matches!(self, GasEstimate::Infinite)
+    }
+}
+
+impl std::str::FromStr for GasEstimate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "infinite" => Ok(GasEstimate::Infinite),
+            _ => s
+                .parse()
+                .map(GasEstimate::Finite)
+                .map_err(|_| format!("invalid gas estimate: {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for GasEstimate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<GasEstimate> for String {
+    fn from(value: GasEstimate) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for GasEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasEstimate::Finite(gas) => write!(f, "{}", gas),
+            GasEstimate::Infinite => f.write_str("infinite"),
+        }
+    }
 }
 
 // Ewasm output in solc result
@@ -374,3 +683,38 @@ pub struct SolcOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contracts: Option<HashMap<String, HashMap<String, SolcContractOutput>>>,
 }
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_entry_enums_round_trip() {
+        let json = r#"{"type":"TypeError","component":"general","severity":"error","message":"Bad"}"#;
+        let entry: SolcErrorEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.error_type, SolcErrorType::TypeError);
+        assert_eq!(entry.component, SolcErrorComponent::General);
+        assert!(entry.is_error());
+        assert!(!entry.is_warning());
+        assert_eq!(serde_json::to_string(&entry).unwrap(), json);
+    }
+
+    #[test]
+    fn test_error_entry_enums_keep_unknown_values() {
+        let json = r#"{"type":"FancyNewError","component":"evmasm","severity":"note","message":"Hmm"}"#;
+        let entry: SolcErrorEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.error_type, SolcErrorType::Unknown("FancyNewError".to_string()));
+        assert_eq!(entry.component, SolcErrorComponent::Unknown("evmasm".to_string()));
+        assert_eq!(entry.severity, SolcSeverity::Unknown("note".to_string()));
+        assert!(!entry.is_error() && !entry.is_warning() && !entry.is_info());
+        assert_eq!(serde_json::to_string(&entry).unwrap(), json);
+    }
+}
@@ -1,7 +1,128 @@
 use ::napi::bindgen_prelude::*;
 use napi_derive::napi;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::diagnostic::render_diagnostics;
+use crate::error::SolcError;
+use crate::models::{SolcErrorEntry, SolcInputDescription, SolcInputSources};
+use crate::solc::{solc_compile_json_async, Solc};
+use crate::store::create_solc;
 
 #[napi]
 pub fn version() -> String {
     "0.1.0".to_string()
 }
+
+/// A solc binary driven over `--standard-json`
+#[napi(js_name = "Solc")]
+pub struct JsSolc {
+    inner: Solc,
+}
+
+#[napi]
+impl JsSolc {
+    #[napi(constructor)]
+    pub fn new(path: String, version: String, timeout_ms: Option<u32>) -> Self {
+        let mut inner = Solc::new(PathBuf::from(path), version);
+        if let Some(timeout_ms) = timeout_ms {
+            inner = inner.with_timeout(Duration::from_millis(timeout_ms as u64));
+        }
+        JsSolc { inner }
+    }
+
+    #[napi(getter)]
+    pub fn path(&self) -> String {
+        self.inner.path.display().to_string()
+    }
+
+    #[napi(getter)]
+    pub fn version(&self) -> String {
+        self.inner.version.clone()
+    }
+
+    /// Compile a standard JSON input and resolve with the standard JSON output
+    ///
+    /// Like the JS compiler, solc diagnostics come back in `errors` instead of rejecting.
+    #[napi(ts_args_type = "input: SolcInputDescription", ts_return_type = "Promise<SolcOutput>")]
+    pub async fn compile(&self, input: serde_json::Value) -> Result<serde_json::Value> {
+        let input: SolcInputDescription = serde_json::from_value(input).map_err(|e| {
+            Error::new(Status::InvalidArg, format!("Invalid solc input: {}", e))
+        })?;
+        output_json(solc_compile_json_async(&self.inner, &input).await)
+    }
+}
+
+/// Look up a verified solc binary for `version` in the default store
+#[napi(js_name = "createSolc")]
+pub async fn create_solc_js(version: String) -> Result<JsSolc> {
+    create_solc(&version)
+        .await
+        .map(|inner| JsSolc { inner })
+        .map_err(to_napi_error)
+}
+
+/// Render `errors` from a solc output the way rustc renders diagnostics
+#[napi(
+    js_name = "renderDiagnostics",
+    ts_args_type = "errors: SolcErrorEntry[], sources: SolcInputSources"
+)]
+pub fn render_diagnostics_js(errors: serde_json::Value, sources: serde_json::Value) -> Result<String> {
+    let errors: Vec<SolcErrorEntry> = serde_json::from_value(errors)
+        .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid solc errors: {}", e)))?;
+    let sources: SolcInputSources = serde_json::from_value(sources)
+        .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid solc sources: {}", e)))?;
+    Ok(render_diagnostics(&errors, &sources))
+}
+
+/// Pass solc's output through unchanged, it already has the `SolcOutput` shape of `solcTypes.ts`
+fn output_json(result: std::result::Result<serde_json::Value, SolcError>) -> Result<serde_json::Value> {
+    result.map_err(to_napi_error)
+}
+
+fn to_napi_error(err: SolcError) -> Error {
+    let status = match err {
+        SolcError::InvalidInput(_)
+        | SolcError::InvalidSettings(_)
+        | SolcError::InvalidEvmVersion(_)
+        | SolcError::InvalidVersion(_)
+        | SolcError::UnsupportedEvmVersion { .. }
+        | SolcError::SourceLoadError(_)
+        | SolcError::SourceHashMismatch { .. } => Status::InvalidArg,
+        _ => Status::GenericFailure,
+    };
+    Error::new(status, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_returns_solc_output_unchanged() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("solc");
+        let output = r#"{"errors":[{"type":"TypeError","component":"general","severity":"error","message":"Bad"}],"sources":{"A.sol":{"id":0,"ast":{"nodeType":"SourceUnit"}}},"contracts":{"A.sol":{"A":{"irOptimized":"object \"A\" {}"}}}}"#;
+        std::fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\necho '{}'\n", output)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let solc = Solc::new(path, "0.8.20".to_string());
+        let input = SolcInputDescription {
+            language: crate::models::SolcLanguage::Solidity,
+            sources: Default::default(),
+            settings: None,
+        };
+
+        let output = output_json(solc_compile_json_async(&solc, &input).await).unwrap();
+        assert_eq!(output["errors"][0]["type"], "TypeError");
+        assert_eq!(output["errors"][0]["severity"], "error");
+        assert_eq!(output["sources"]["A.sol"]["ast"]["nodeType"], "SourceUnit");
+        // Fields the Rust models do not cover survive
+        assert_eq!(output["contracts"]["A.sol"]["A"]["irOptimized"], "object \"A\" {}");
+
+        let err = output_json(Err(SolcError::InvalidInput("two Yul sources".to_string()))).unwrap_err();
+        assert_eq!(err.status, Status::InvalidArg);
+        assert!(err.reason.contains("two Yul sources"));
+    }
+}
@@ -0,0 +1,575 @@
+use crate::error::SolcError;
+use crate::evm_version::parse_solc_version;
+use crate::graph::ModuleGraph;
+use crate::solc::Solc;
+use crate::store::SolcStore;
+use semver::Version;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+impl Comparator {
+    fn new(op: Op, (major, minor, patch): (u64, u64, u64)) -> Self {
+        Comparator {
+            op,
+            version: Version::new(major, minor, patch),
+        }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let ordering = (version.major, version.minor, version.patch).cmp(&(
+            self.version.major,
+            self.version.minor,
+            self.version.patch,
+        ));
+        match self.op {
+            Op::Eq => ordering.is_eq(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Eq => "",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        };
+        write!(f, "{}{}", op, self.version)
+    }
+}
+
+/// A version such as `0.8`, `0.8.x` or `*` whose missing components match anything
+#[derive(Debug, Clone, Copy)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+}
+
+impl Partial {
+    fn parse(version: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = version.split('.').collect();
+        if version.is_empty() || parts.len() > 3 {
+            return Err(format!("invalid version `{}`", version));
+        }
+        let mut components = [None; 3];
+        for (component, part) in components.iter_mut().zip(&parts) {
+            if matches!(*part, "x" | "X" | "*") {
+                break;
+            }
+            *component = Some(
+                part.parse::<u64>()
+                    .map_err(|_| format!("invalid version `{}`", version))?,
+            );
+        }
+        let [major, minor, patch] = components;
+        Ok(Partial { major, minor, patch })
+    }
+
+    /// The lowest version this partial covers
+    fn floor(&self) -> (u64, u64, u64) {
+        (
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+        )
+    }
+
+    /// The lowest version above everything this partial covers
+    fn next(&self, major: u64) -> (u64, u64, u64) {
+        match (self.minor, self.patch) {
+            (None, _) => (major + 1, 0, 0),
+            (Some(minor), None) => (major, minor + 1, 0),
+            (Some(minor), Some(patch)) => (major, minor, patch + 1),
+        }
+    }
+
+    /// The comparators `op` applied to this partial expands to, following solc
+    fn expand(&self, op: &str) -> Result<Vec<Comparator>, String> {
+        let nothing = vec![Comparator::new(Op::Lt, (0, 0, 0))];
+        let Some(major) = self.major else {
+            // `*`, `>=*` and friends match every version, `>*` and `<*` none
+            return match op {
+                "" | "=" | ">=" | "<=" | "^" | "~" => Ok(vec![]),
+                ">" | "<" => Ok(nothing),
+                _ => Err(format!("unknown operator `{}`", op)),
+            };
+        };
+        let floor = self.floor();
+        let exact = self.patch.is_some();
+        let comparators = match op {
+            "" | "=" if exact => vec![Comparator::new(Op::Eq, floor)],
+            "" | "=" => vec![
+                Comparator::new(Op::Ge, floor),
+                Comparator::new(Op::Lt, self.next(major)),
+            ],
+            ">=" => vec![Comparator::new(Op::Ge, floor)],
+            ">" if exact => vec![Comparator::new(Op::Gt, floor)],
+            ">" => vec![Comparator::new(Op::Ge, self.next(major))],
+            "<" => vec![Comparator::new(Op::Lt, floor)],
+            "<=" if exact => vec![Comparator::new(Op::Le, floor)],
+            "<=" => vec![Comparator::new(Op::Lt, self.next(major))],
+            "^" => {
+                let upper = match (major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+                    (0, Some(minor), _) => (0, minor + 1, 0),
+                    _ => (major + 1, 0, 0),
+                };
+                vec![Comparator::new(Op::Ge, floor), Comparator::new(Op::Lt, upper)]
+            }
+            "~" => {
+                let upper = match self.minor {
+                    Some(minor) => (major, minor + 1, 0),
+                    None => (major + 1, 0, 0),
+                };
+                vec![Comparator::new(Op::Ge, floor), Comparator::new(Op::Lt, upper)]
+            }
+            _ => return Err(format!("unknown operator `{}`", op)),
+        };
+        Ok(comparators)
+    }
+}
+
+/// A `pragma solidity` version expression, e.g. `^0.8.0` or `>=0.6.2 <0.9.0 || 0.5.17`
+///
+/// Follows solc: comparators separated by spaces must all match, `||` separates
+/// alternatives, `a - b` is an inclusive range, and partial versions such as `0.8` or
+/// `0.8.x` cover every version they prefix. Prereleases and build metadata of the checked
+/// version are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityVersionReq {
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+impl SolidityVersionReq {
+    /// The requirement every version satisfies
+    pub fn any() -> Self {
+        SolidityVersionReq {
+            alternatives: vec![vec![]],
+        }
+    }
+
+    pub fn parse(expression: &str) -> Result<Self, SolcError> {
+        Self::parse_expression(expression).map_err(SolcError::InvalidPragma)
+    }
+
+    fn parse_expression(expression: &str) -> Result<Self, String> {
+        let in_expression = |reason: String| format!("{} in `{}`", reason, expression.trim());
+        let mut alternatives = vec![];
+        for alternative in expression.split("||") {
+            let tokens = tokenize(alternative);
+            if tokens.is_empty() {
+                return Err(in_expression("empty version range".to_string()));
+            }
+            let mut comparators = vec![];
+            let mut i = 0;
+            while i < tokens.len() {
+                if tokens.get(i + 1).map(String::as_str) == Some("-") {
+                    let high = tokens
+                        .get(i + 2)
+                        .ok_or_else(|| in_expression("missing upper bound of `-`".to_string()))?;
+                    for (op, version) in [(">=", &tokens[i]), ("<=", high)] {
+                        let partial = Partial::parse(version).map_err(in_expression)?;
+                        comparators.extend(partial.expand(op).map_err(in_expression)?);
+                    }
+                    i += 3;
+                } else {
+                    let token = &tokens[i];
+                    let split = token
+                        .find(|c: char| !is_operator(c))
+                        .unwrap_or(token.len());
+                    let partial = Partial::parse(&token[split..]).map_err(in_expression)?;
+                    comparators.extend(partial.expand(&token[..split]).map_err(in_expression)?);
+                    i += 1;
+                }
+            }
+            alternatives.push(comparators);
+        }
+        Ok(SolidityVersionReq { alternatives })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.alternatives
+            .iter()
+            .any(|comparators| comparators.iter().all(|comparator| comparator.matches(version)))
+    }
+
+    /// The requirement versions must meet to satisfy both `self` and `other`
+    pub fn intersect(&self, other: &Self) -> Self {
+        let alternatives = self
+            .alternatives
+            .iter()
+            .flat_map(|a| {
+                other
+                    .alternatives
+                    .iter()
+                    .map(move |b| a.iter().chain(b).cloned().collect())
+            })
+            .collect();
+        SolidityVersionReq { alternatives }
+    }
+
+    /// The highest of `versions` that satisfies the requirement
+    pub fn highest_match<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a Version>,
+    ) -> Option<&'a Version> {
+        versions.into_iter().filter(|version| self.matches(version)).max()
+    }
+}
+
+impl FromStr for SolidityVersionReq {
+    type Err = SolcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SolidityVersionReq::parse(s)
+    }
+}
+
+impl fmt::Display for SolidityVersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, comparators) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" || ")?;
+            }
+            if comparators.is_empty() {
+                f.write_str("*")?;
+            }
+            for (j, comparator) in comparators.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{}", comparator)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_operator(c: char) -> bool {
+    matches!(c, '^' | '~' | '<' | '>' | '=')
+}
+
+/// Split a range on whitespace, keeping `>= 0.8.0` together as one comparator
+fn tokenize(range: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut pending_operator = String::new();
+    for token in range.split_whitespace() {
+        if token.chars().all(is_operator) {
+            pending_operator.push_str(token);
+        } else {
+            tokens.push(format!("{}{}", pending_operator, token));
+            pending_operator.clear();
+        }
+    }
+    if !pending_operator.is_empty() {
+        tokens.push(pending_operator);
+    }
+    tokens
+}
+
+/// The expressions of every `pragma solidity` directive in `source`, comments skipped
+pub fn pragma_expressions(source: &str) -> Vec<String> {
+    let code = strip_comments(source);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut expressions = vec![];
+    let mut rest = code.as_str();
+    while let Some(start) = rest.find("pragma") {
+        let keyword_start = !rest[..start].ends_with(is_ident);
+        let after = &rest[start + "pragma".len()..];
+        let directive = after.trim_start();
+        let is_solidity = keyword_start
+            && directive.len() < after.len()
+            && directive
+                .strip_prefix("solidity")
+                .is_some_and(|body| !body.starts_with(is_ident));
+        if !is_solidity {
+            rest = after;
+            continue;
+        }
+        let body = &directive["solidity".len()..];
+        let end = body.find(';').unwrap_or(body.len());
+        expressions.push(body[..end].trim().to_string());
+        rest = &body[end..];
+    }
+    expressions
+}
+
+/// The requirement of every `pragma solidity` in `source`, or `None` if it has none
+pub fn source_version_req(source: &str) -> Result<Option<SolidityVersionReq>, SolcError> {
+    pragma_expressions(source)
+        .iter()
+        .map(|expression| SolidityVersionReq::parse(expression))
+        .try_fold(None, |combined: Option<SolidityVersionReq>, req| {
+            let req = req?;
+            Ok(Some(match combined {
+                Some(combined) => combined.intersect(&req),
+                None => req,
+            }))
+        })
+}
+
+/// A file's pragmas as written and the requirement they add up to
+struct FileRequirement<'a> {
+    file: &'a str,
+    pragma: String,
+    req: SolidityVersionReq,
+}
+
+fn file_requirements(graph: &ModuleGraph) -> Result<Vec<FileRequirement<'_>>, SolcError> {
+    let mut files: Vec<&String> = graph.keys().collect();
+    files.sort();
+    let mut requirements = vec![];
+    for file in files {
+        let expressions = pragma_expressions(&graph[file].raw_code);
+        if expressions.is_empty() {
+            continue;
+        }
+        let mut req = SolidityVersionReq::any();
+        for expression in &expressions {
+            let parsed = SolidityVersionReq::parse_expression(expression)
+                .map_err(|reason| SolcError::InvalidPragma(format!("{} ({})", reason, file)))?;
+            req = req.intersect(&parsed);
+        }
+        requirements.push(FileRequirement {
+            file,
+            pragma: expressions.join(" "),
+            req,
+        });
+    }
+    Ok(requirements)
+}
+
+/// The range of solc versions every module of `graph` accepts
+///
+/// Modules without a `pragma solidity` accept any version.
+pub fn graph_version_req(graph: &ModuleGraph) -> Result<SolidityVersionReq, SolcError> {
+    Ok(file_requirements(graph)?
+        .iter()
+        .fold(SolidityVersionReq::any(), |combined, file| combined.intersect(&file.req)))
+}
+
+/// Pick the highest of `installed` that satisfies every `pragma solidity` in `graph`
+///
+/// If none does, the error names the files that conflict: those no installed version
+/// satisfies on their own, otherwise the pairs of files that share no installed version,
+/// otherwise every file with a pragma.
+pub fn select_solc_version(graph: &ModuleGraph, installed: &[Version]) -> Result<Version, SolcError> {
+    let requirements = file_requirements(graph)?;
+    let combined = requirements
+        .iter()
+        .fold(SolidityVersionReq::any(), |combined, file| combined.intersect(&file.req));
+    if let Some(version) = combined.highest_match(installed) {
+        return Ok(version.clone());
+    }
+
+    let matching: Vec<Vec<bool>> = requirements
+        .iter()
+        .map(|file| installed.iter().map(|version| file.req.matches(version)).collect())
+        .collect();
+    let mut conflicting: Vec<usize> = (0..requirements.len())
+        .filter(|&i| !matching[i].contains(&true))
+        .collect();
+    if conflicting.is_empty() {
+        for i in 0..requirements.len() {
+            for j in i + 1..requirements.len() {
+                if !matching[i].iter().zip(&matching[j]).any(|(a, b)| *a && *b) {
+                    conflicting.extend([i, j]);
+                }
+            }
+        }
+        conflicting.sort();
+        conflicting.dedup();
+    }
+    if conflicting.is_empty() {
+        conflicting = (0..requirements.len()).collect();
+    }
+    Err(SolcError::IncompatiblePragmas(
+        conflicting
+            .into_iter()
+            .map(|i| (requirements[i].file.to_string(), requirements[i].pragma.clone()))
+            .collect(),
+    ))
+}
+
+impl SolcStore {
+    /// Get a verified [`Solc`] for the highest installed version every module in `graph` accepts
+    pub fn solc_for_graph(&self, graph: &ModuleGraph) -> Result<Solc, SolcError> {
+        let installed: Vec<Version> = self
+            .installed_versions()?
+            .iter()
+            .filter_map(|version| parse_solc_version(version))
+            .collect();
+        let version = select_solc_version(graph, &installed)?;
+        self.get(&version.to_string())
+    }
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut code = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                code.push(' ');
+            }
+            _ => code.push(c),
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::ModuleInfo;
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn graph(files: &[(&str, &str)]) -> ModuleGraph {
+        files
+            .iter()
+            .map(|(file, code)| {
+                let module = ModuleInfo {
+                    code: code.to_string(),
+                    raw_code: code.to_string(),
+                    imported_ids: vec![],
+                };
+                (file.to_string(), module)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parses_ranges() {
+        let cases = [
+            ("^0.8.0", ">=0.8.0 <0.9.0"),
+            ("^0.0.3", ">=0.0.3 <0.0.4"),
+            ("^1.2", ">=1.2.0 <2.0.0"),
+            ("~0.8.1", ">=0.8.1 <0.9.0"),
+            ("0.8", ">=0.8.0 <0.9.0"),
+            ("0.8.x", ">=0.8.0 <0.9.0"),
+            ("=0.8.20", "0.8.20"),
+            (">0.7", ">=0.8.0"),
+            ("<=0.7", "<0.8.0"),
+            (">= 0.6.2 < 0.9.0", ">=0.6.2 <0.9.0"),
+            ("0.5.0 - 0.6", ">=0.5.0 <0.7.0"),
+            ("^0.7.6 || 0.8.x", ">=0.7.6 <0.8.0 || >=0.8.0 <0.9.0"),
+            ("*", "*"),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                SolidityVersionReq::parse(expression).unwrap().to_string(),
+                expected,
+                "{}",
+                expression
+            );
+        }
+
+        let req: SolidityVersionReq = ">=0.6.2 <0.8.0 || 0.8.20".parse().unwrap();
+        assert!(req.matches(&v("0.7.6")));
+        assert!(req.matches(&v("0.8.20+commit.a1b79de6")));
+        assert!(!req.matches(&v("0.8.19")));
+        assert_eq!(
+            req.highest_match(&[v("0.6.1"), v("0.7.6"), v("0.8.19")]),
+            Some(&v("0.7.6"))
+        );
+
+        assert!(matches!(
+            SolidityVersionReq::parse("=>0.8.0"),
+            Err(SolcError::InvalidPragma(_))
+        ));
+        assert!(SolidityVersionReq::parse("^0.8.0 ||").is_err());
+        assert!(SolidityVersionReq::parse("0.8.0.1").is_err());
+    }
+
+    #[test]
+    fn test_reads_pragmas_outside_comments() {
+        let source = r#"
+            // SPDX-License-Identifier: MIT
+            // pragma solidity ^0.4.0;
+            /* pragma solidity 0.5.0; */
+            pragma solidity >=0.8.0;
+            pragma abicoder v2;
+            pragma solidity <0.8.20;
+            contract A { uint pragmatic; }
+        "#;
+        assert_eq!(pragma_expressions(source), vec![">=0.8.0", "<0.8.20"]);
+        let req = source_version_req(source).unwrap().unwrap();
+        assert!(req.matches(&v("0.8.19")));
+        assert!(!req.matches(&v("0.8.20")));
+        assert_eq!(source_version_req("contract A {}").unwrap(), None);
+    }
+
+    #[test]
+    fn test_selects_version_for_graph() {
+        let installed = [v("0.7.6"), v("0.8.19"), v("0.8.20"), v("0.8.24")];
+        let modules = graph(&[
+            ("/src/A.sol", "pragma solidity ^0.8.0;\nimport './B.sol';"),
+            ("/src/B.sol", "pragma solidity >=0.7.0 <0.8.21;"),
+            ("/src/C.sol", "contract C {}"),
+        ]);
+        assert_eq!(select_solc_version(&modules, &installed).unwrap(), v("0.8.20"));
+        assert_eq!(
+            graph_version_req(&modules).unwrap().to_string(),
+            ">=0.8.0 <0.9.0 >=0.7.0 <0.8.21"
+        );
+
+        let conflicting = graph(&[
+            ("/src/A.sol", "pragma solidity ^0.8.0;"),
+            ("/src/B.sol", "pragma solidity >=0.7.0 <0.8.21;"),
+            ("/src/Old.sol", "pragma solidity ^0.7.0;"),
+        ]);
+        match select_solc_version(&conflicting, &installed) {
+            Err(SolcError::IncompatiblePragmas(files)) => assert_eq!(
+                files,
+                vec![
+                    ("/src/A.sol".to_string(), "^0.8.0".to_string()),
+                    ("/src/Old.sol".to_string(), "^0.7.0".to_string()),
+                ]
+            ),
+            other => panic!("Expected IncompatiblePragmas, got {:?}", other),
+        }
+
+        let too_new = graph(&[("/src/New.sol", "pragma solidity ^0.9.0;")]);
+        let err = select_solc_version(&too_new, &installed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No installed solc satisfies the pragmas of /src/New.sol (^0.9.0)"
+        );
+    }
+}
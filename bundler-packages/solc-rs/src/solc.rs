@@ -1,9 +1,15 @@
+use crate::cache::{CacheKey, CompileCache};
 use crate::error::SolcError;
+use crate::evm_version::parse_solc_version;
+use crate::loader::{resolve_sources, SourceLoader};
 use crate::models::{SolcInputDescription, SolcOutput};
+use crate::validate::validate;
 use std::path::PathBuf;
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::process::{Command, Output, Stdio};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
 
 /// The Solc compiler interface
 #[derive(Debug, Clone)]
@@ -12,6 +18,12 @@ pub struct Solc {
     pub path: Arc<PathBuf>,
     /// The version of the solc compiler
     pub version: String,
+    /// Maximum time an async compilation may run before solc is killed
+    pub timeout: Option<Duration>,
+    /// Loaders used to inline sources that are only given by `urls`
+    pub loaders: Vec<Arc<dyn SourceLoader>>,
+    /// Cache consulted before spawning solc and filled with successful outputs
+    pub cache: Option<Arc<dyn CompileCache>>,
 }
 
 impl Solc {
@@ -20,19 +32,74 @@ impl Solc {
         Solc {
             path: Arc::new(path),
             version,
+            timeout: None,
+            loaders: Vec::new(),
+            cache: None,
         }
     }
 
+    /// Kill async compilations that run longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Resolve `urls` sources through `loader`, after any loaders added before it
+    pub fn with_loader(mut self, loader: impl SourceLoader + 'static) -> Self {
+        self.loaders.push(Arc::new(loader));
+        self
+    }
+
+    /// Reuse outputs from `cache` for inputs this binary compiled before
+    pub fn with_cache(mut self, cache: impl CompileCache + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Check `input` against what this compiler version supports without spawning solc
+    ///
+    /// The language checks of [`SolcInputDescription::check_language`] always run; the EVM
+    /// version and [`validate`] checks are skipped for versions that cannot be parsed as semver.
+    pub fn check_input(&self, input: &SolcInputDescription) -> Result<(), SolcError> {
+        input.check_language()?;
+        let version = match parse_solc_version(&self.version) {
+            Some(version) => version,
+            None => return Ok(()),
+        };
+        if let Some(evm_version) = input.settings.as_ref().and_then(|settings| settings.evm_version) {
+            evm_version.check(&version)?;
+        }
+        if let Some(settings) = &input.settings {
+            let issues = validate(settings, &version);
+            if !issues.is_empty() {
+                return Err(SolcError::InvalidSettings(issues));
+            }
+        }
+        Ok(())
+    }
+
     /// Compile Solidity code
     pub fn compile(&self, input: &SolcInputDescription) -> Result<SolcOutput, SolcError> {
         solc_compile(self, input)
     }
+
+    /// Compile Solidity code without blocking the async runtime
+    pub async fn compile_async(&self, input: &SolcInputDescription) -> Result<SolcOutput, SolcError> {
+        solc_compile_async(self, input).await
+    }
 }
 
 /// Compile Solidity code using the provided solc compiler
 pub fn solc_compile(solc: &Solc, input: &SolcInputDescription) -> Result<SolcOutput, SolcError> {
+    solc.check_input(input)?;
+    let input = resolve_sources(input, &solc.loaders)?;
+    let cache_key = cache_key(solc, &input)?;
+    if let Some(output) = read_cache(solc, cache_key.as_ref()) {
+        return Ok(output);
+    }
+
     // Serialize the input to JSON
-    let input_json = serde_json::to_string(input)
+    let input_json = serde_json::to_string(&*input)
         .map_err(|e| SolcError::SerializationError(e.to_string()))?;
     
     let mut child = Command::new(&*solc.path)
@@ -53,35 +120,182 @@ pub fn solc_compile(solc: &Solc, input: &SolcInputDescription) -> Result<SolcOut
     let output = child
         .wait_with_output()
         .map_err(|e| SolcError::CompilationError(format!("Failed to read solc output: {}", e)))?;
-    
+
+    let output = parse_solc_output(output)?;
+    write_cache(solc, cache_key.as_ref(), &output);
+    check_diagnostics(output)
+}
+
+/// Compile Solidity code using the provided solc compiler on the tokio runtime
+///
+/// The child process is killed if the returned future is dropped or if it runs longer than
+/// [`Solc::timeout`], in which case [`SolcError::Timeout`] reports how long it ran. Loading
+/// sources and reading or writing the cache happen on the blocking thread pool.
+pub async fn solc_compile_async(solc: &Solc, input: &SolcInputDescription) -> Result<SolcOutput, SolcError> {
+    solc.check_input(input)?;
+    let (input, cache_key, cached) = {
+        let (solc, input) = (solc.clone(), input.clone());
+        run_blocking(move || {
+            let input = resolve_sources(&input, &solc.loaders)?.into_owned();
+            let cache_key = cache_key(&solc, &input)?;
+            let cached = read_cache(&solc, cache_key.as_ref());
+            Ok((input, cache_key, cached))
+        })
+        .await?
+    };
+    if let Some(output) = cached {
+        return Ok(output);
+    }
+
+    let output = parse_solc_output(run_solc_async(solc, &input).await?)?;
+    let output = match solc.cache {
+        Some(_) => {
+            let solc = solc.clone();
+            run_blocking(move || {
+                write_cache(&solc, cache_key.as_ref(), &output);
+                Ok(output)
+            })
+            .await?
+        }
+        None => output,
+    };
+    check_diagnostics(output)
+}
+
+/// Compile `input` and resolve with solc's output JSON exactly as solc wrote it
+///
+/// Unlike [`solc_compile_async`] nothing is dropped: fields this crate does not model are
+/// kept, and error diagnostics come back in `errors` next to the `sources` solc still
+/// produced, as the JS compiler returns them. The cache is not used.
+pub(crate) async fn solc_compile_json_async(
+    solc: &Solc,
+    input: &SolcInputDescription,
+) -> Result<serde_json::Value, SolcError> {
+    solc.check_input(input)?;
+    let input = {
+        let (solc, input) = (solc.clone(), input.clone());
+        run_blocking(move || Ok(resolve_sources(&input, &solc.loaders)?.into_owned())).await?
+    };
+    let stdout = solc_stdout(run_solc_async(solc, &input).await?)?;
+    serde_json::from_str(&stdout)
+        .map_err(|e| SolcError::SerializationError(format!("Failed to parse solc output: {}", e)))
+}
+
+/// Run solc on an input whose sources are already resolved
+///
+/// The child process is killed if the returned future is dropped or once [`Solc::timeout`]
+/// has passed.
+async fn run_solc_async(solc: &Solc, input: &SolcInputDescription) -> Result<Output, SolcError> {
+    // Serialize the input to JSON
+    let input_json = serde_json::to_string(input)
+        .map_err(|e| SolcError::SerializationError(e.to_string()))?;
+
+    let started = Instant::now();
+    let mut child = tokio::process::Command::new(&*solc.path)
+        .arg("--standard-json")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| SolcError::CompilationError(format!("Failed to execute solc: {}", e)))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| SolcError::CompilationError("Failed to open solc stdin".to_string()))?;
+
+    let run = async move {
+        stdin
+            .write_all(input_json.as_bytes())
+            .await
+            .map_err(|e| SolcError::CompilationError(format!("Failed to write solc standard JSON input: {}", e)))?;
+        // Close stdin so solc knows the input is complete
+        drop(stdin);
+        child
+            .wait_with_output()
+            .await
+            .map_err(|e| SolcError::CompilationError(format!("Failed to read solc output: {}", e)))
+    };
+
+    match solc.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, run)
+            .await
+            .map_err(|_| SolcError::Timeout(started.elapsed()))?,
+        None => run.await,
+    }
+}
+
+/// Run filesystem work off the async runtime
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, SolcError> + Send + 'static,
+) -> Result<T, SolcError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| SolcError::Unknown(format!("Blocking task failed: {}", e)))?
+}
+
+/// The key of `input` in the compiler's cache, `None` without a cache
+fn cache_key(solc: &Solc, input: &SolcInputDescription) -> Result<Option<CacheKey>, SolcError> {
+    solc.cache
+        .as_ref()
+        .map(|_| CacheKey::new(solc, input))
+        .transpose()
+}
+
+fn read_cache(solc: &Solc, key: Option<&CacheKey>) -> Option<SolcOutput> {
+    match (&solc.cache, key) {
+        // An unreadable cache only costs a recompile, like a failed write
+        (Some(cache), Some(key)) => cache.get(key).ok().flatten(),
+        _ => None,
+    }
+}
+
+/// Store `output` unless solc reported an error in it
+fn write_cache(solc: &Solc, key: Option<&CacheKey>, output: &SolcOutput) {
+    if has_errors(output) {
+        return;
+    }
+    if let (Some(cache), Some(key)) = (&solc.cache, key) {
+        // The output is already compiled; a failed write only costs a recompile later
+        let _ = cache.put(key, output);
+    }
+}
+
+/// Turn the raw output of a `solc --standard-json` process into a [`SolcOutput`]
+fn parse_solc_output(output: Output) -> Result<SolcOutput, SolcError> {
+    serde_json::from_str(&solc_stdout(output)?)
+        .map_err(|e| SolcError::SerializationError(format!("Failed to parse solc output: {}", e)))
+}
+
+/// The output JSON of a `solc --standard-json` process that exited successfully
+fn solc_stdout(output: Output) -> Result<String, SolcError> {
     // Check if the command executed successfully
     if !output.status.success() {
         return Err(SolcError::CompilationError(
             String::from_utf8_lossy(&output.stderr).to_string()
         ));
     }
-    
-    // Parse the output JSON
-    let output_json = String::from_utf8_lossy(&output.stdout);
-    let solc_output: SolcOutput = serde_json::from_str(&output_json)
-        .map_err(|e| SolcError::SerializationError(format!("Failed to parse solc output: {}", e)))?;
-    
-    // Check for compilation errors
-    if let Some(errors) = &solc_output.errors {
-        for error in errors {
-            if error.severity == "error" {
-                return Err(SolcError::CompilationError(error.message.clone()));
-            }
-        }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn has_errors(output: &SolcOutput) -> bool {
+    output.errors.iter().flatten().any(|error| error.is_error())
+}
+
+/// Fail with every diagnostic if any of them is an error
+fn check_diagnostics(output: SolcOutput) -> Result<SolcOutput, SolcError> {
+    if has_errors(&output) {
+        return Err(SolcError::Diagnostics(output.errors.unwrap_or_default()));
     }
-    
-    Ok(solc_output)
+    Ok(output)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{SolcLanguage, SolcInputSource};
+    use crate::evm_version::EvmVersion;
+    use crate::models::{SolcErrorType, SolcLanguage, SolcInputSource, SolcSettings};
     use std::collections::HashMap;
     use std::env;
     
@@ -107,7 +321,248 @@ mod tests {
         
         None
     }
+
+    /// Writes an executable shell script standing in for solc
+    #[cfg(unix)]
+    fn fake_solc(dir: &std::path::Path, script: &str) -> Solc {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("solc");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Solc::new(path, "0.8.20".to_string())
+    }
+
+    #[cfg(unix)]
+    fn empty_input() -> SolcInputDescription {
+        SolcInputDescription {
+            language: SolcLanguage::Solidity,
+            sources: HashMap::new(),
+            settings: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_async_reads_standard_json_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), r#"cat > /dev/null; echo '{"sources":{"A.sol":{"id":0}}}'"#);
+
+        let output = solc.compile_async(&empty_input()).await.unwrap();
+        assert_eq!(output.sources.unwrap()["A.sol"].id, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_async_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), "sleep 10").with_timeout(Duration::from_millis(200));
+
+        let started = Instant::now();
+        match solc.compile_async(&empty_input()).await {
+            Err(SolcError::Timeout(elapsed)) => {
+                assert!(elapsed >= Duration::from_millis(200));
+                assert!(started.elapsed() < Duration::from_secs(5));
+            }
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dropping_compile_kills_solc() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let solc = fake_solc(dir.path(), &format!("echo $$ > {}; exec sleep 30", pid_file.display()));
+        let input = empty_input();
+
+        let mut compile = Box::pin(solc.compile_async(&input));
+        let started = Instant::now();
+        let pid = loop {
+            tokio::select! {
+                result = &mut compile => panic!("Expected solc to still be running, got {:?}", result),
+                _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+            }
+            if let Some(pid) = std::fs::read_to_string(&pid_file).ok().filter(|pid| pid.ends_with('\n')) {
+                break pid.trim().to_string();
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "solc never started");
+        };
+        drop(compile);
+
+        // Killed processes may linger as zombies until tokio reaps them
+        let running = || {
+            let ps = Command::new("ps").args(["-o", "stat=", "-p", &pid]).output().unwrap();
+            let stat = String::from_utf8_lossy(&ps.stdout).trim().to_string();
+            !stat.is_empty() && !stat.starts_with('Z')
+        };
+        let started = Instant::now();
+        while running() {
+            assert!(started.elapsed() < Duration::from_secs(5), "solc {} is still running", pid);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
     
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_returns_every_diagnostic() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = r#"{"errors":[
+            {"type":"Warning","component":"general","severity":"warning","message":"Unused local variable.","sourceLocation":{"file":"A.sol","start":10,"end":20}},
+            {"type":"DeclarationError","component":"general","severity":"error","errorCode":"7576","message":"Undeclared identifier.","formattedMessage":"DeclarationError: Undeclared identifier.\n --> A.sol:2:5:\n","sourceLocation":{"file":"A.sol","start":30,"end":33}},
+            {"type":"TypeError","component":"general","severity":"error","message":"Wrong argument count.","sourceLocation":{"file":"B.sol","start":1,"end":4}}
+        ]}"#;
+        let output_path = dir.path().join("output.json");
+        std::fs::write(&output_path, output).unwrap();
+        let solc = fake_solc(dir.path(), &format!("cat > /dev/null; cat {}", output_path.display()));
+
+        let err = solc.compile(&empty_input()).unwrap_err();
+        let diagnostics = err.diagnostics().expect("Expected Diagnostics");
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0].is_warning());
+        assert_eq!(diagnostics[1].error_type, SolcErrorType::DeclarationError);
+        assert_eq!(diagnostics[1].error_code.as_deref(), Some("7576"));
+        assert_eq!(diagnostics[2].source_location.as_ref().unwrap().file, "B.sol");
+
+        let message = err.to_string();
+        assert!(message.starts_with("Compilation failed with 2 error(s)"));
+        assert!(message.contains("DeclarationError: Undeclared identifier.\n --> A.sol:2:5:"));
+        assert!(message.contains("TypeError: Wrong argument count."));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unsupported_evm_version_fails_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("spawned");
+        let mut solc = fake_solc(dir.path(), &format!("touch {}", marker.display()));
+        solc.version = "0.8.19".to_string();
+        let mut input = empty_input();
+        input.settings = Some(SolcSettings {
+            evm_version: Some(EvmVersion::Cancun),
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            solc.compile(&input),
+            Err(SolcError::UnsupportedEvmVersion { .. })
+        ));
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unsupported_settings_fail_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("spawned");
+        let mut solc = fake_solc(dir.path(), &format!("touch {}", marker.display()));
+        solc.version = "0.8.10".to_string();
+        let mut input = empty_input();
+        input.settings = Some(SolcSettings {
+            via_ir: Some(true),
+            ..Default::default()
+        });
+
+        match solc.compile(&input) {
+            Err(SolcError::InvalidSettings(issues)) => {
+                assert_eq!(issues.len(), 1);
+                assert_eq!(issues[0].field, "settings.viaIR");
+            }
+            other => panic!("Expected InvalidSettings, got {:?}", other),
+        }
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_invalid_yul_input_fails_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("spawned");
+        let solc = fake_solc(dir.path(), &format!("touch {}", marker.display()));
+        let input = SolcInputDescription::builder()
+            .language(SolcLanguage::Yul)
+            .source("a.yul", "{}")
+            .source("b.yul", "{}")
+            .build();
+
+        assert!(matches!(solc.compile(&input), Err(SolcError::InvalidInput(_))));
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_url_sources_are_inlined_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let stdin_path = dir.path().join("stdin.json");
+        let solc = fake_solc(
+            dir.path(),
+            &format!("cat > {}; echo '{{}}'", stdin_path.display()),
+        );
+        let loader = crate::loader::MemoryLoader::new();
+        loader.insert("mem://A.sol", "contract A {}");
+        let solc = solc.with_loader(loader);
+        let input = SolcInputDescription::builder()
+            .source_urls("A.sol", vec!["mem://A.sol".to_string()])
+            .build();
+
+        solc.compile(&input).unwrap();
+        let sent: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&stdin_path).unwrap()).unwrap();
+        assert_eq!(sent["sources"]["A.sol"]["content"], "contract A {}");
+        assert!(sent["sources"]["A.sol"].get("urls").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cached_outputs_skip_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let runs = dir.path().join("runs");
+        let solc = fake_solc(
+            dir.path(),
+            &format!(
+                r#"cat > /dev/null; echo run >> {}; echo '{{"sources":{{"A.sol":{{"id":0}}}}}}'"#,
+                runs.display()
+            ),
+        )
+        .with_cache(crate::cache::DiskCache::new(dir.path().join("cache")));
+        let input = SolcInputDescription::builder().source("A.sol", "contract A {}").build();
+
+        let compiled = solc.compile(&input).unwrap();
+        assert_eq!(solc.compile_async(&input).await.unwrap(), compiled);
+        assert_eq!(solc.compile(&input).unwrap(), compiled);
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 1);
+
+        let edited = SolcInputDescription::builder().source("A.sol", "contract A { }").build();
+        solc.compile(&edited).unwrap();
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[derive(Debug)]
+    struct BrokenCache;
+
+    #[cfg(unix)]
+    impl CompileCache for BrokenCache {
+        fn get(&self, _key: &CacheKey) -> Result<Option<SolcOutput>, SolcError> {
+            Err(SolcError::Unknown("read-only cache".to_string()))
+        }
+
+        fn put(&self, _key: &CacheKey, _output: &SolcOutput) -> Result<(), SolcError> {
+            Err(SolcError::Unknown("read-only cache".to_string()))
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cache_failures_are_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), r#"cat > /dev/null; echo '{"sources":{"A.sol":{"id":0}}}'"#)
+            .with_cache(BrokenCache);
+        let input = SolcInputDescription::builder().source("A.sol", "contract A {}").build();
+
+        assert_eq!(solc.compile(&input).unwrap().sources.unwrap()["A.sol"].id, 0);
+        assert_eq!(solc.compile_async(&input).await.unwrap().sources.unwrap()["A.sol"].id, 0);
+    }
+
     #[test]
     fn test_solc_compile_if_available() {
         // Skip test if solc is not available
@@ -156,6 +611,7 @@ mod tests {
                 keccak256: None,
                 urls: None,
                 content: Some(contract.to_string()),
+                ast: None,
             },
         );
         
@@ -169,7 +625,7 @@ mod tests {
         match solc.compile(&input) {
             Ok(output) => {
                 // Check if the compilation was successful
-                assert!(output.errors.is_none() || output.errors.as_ref().unwrap().iter().all(|e| e.severity != "error"));
+                assert!(output.errors.is_none() || output.errors.as_ref().unwrap().iter().all(|e| !e.is_error()));
                 
                 // Check if the contract was compiled
                 if let Some(contracts) = output.contracts {
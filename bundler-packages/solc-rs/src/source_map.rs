@@ -0,0 +1,321 @@
+use crate::disassembler::{decode_object, disassemble};
+use crate::error::SolcError;
+use crate::models::{SolcBytecodeOutput, SolcDeployedBytecodeOutput, SolcGeneratedSource, SolcOutput};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Whether an instruction jumps into or out of a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jump {
+    /// `i`, jump into a function
+    In,
+    /// `o`, return from a function
+    Out,
+    /// `-`, a regular jump or no jump at all
+    Regular,
+}
+
+/// One decoded `s:l:f:j:m` source map entry, i.e. the source range of one instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceMapEntry {
+    /// Byte offset in the source file, `-1` if unknown
+    pub start: i64,
+    /// Byte length in the source file, `-1` if unknown
+    pub length: i64,
+    /// Source id from the `sources` table, `-1` for compiler-internal code
+    pub source_index: i64,
+    pub jump: Jump,
+    pub modifier_depth: u32,
+}
+
+impl SourceMapEntry {
+    /// Whether the instruction maps to user or generated source
+    pub fn has_source(&self) -> bool {
+        self.source_index >= 0 && self.start >= 0 && self.length >= 0
+    }
+}
+
+/// A decompressed solc source map with one entry per instruction
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    pub entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    /// Expand a compressed `s:l:f:j:m;...` source map
+    ///
+    /// Empty fields and missing trailing fields inherit the value of the previous entry.
+    pub fn parse(source_map: &str) -> Result<Self, SolcError> {
+        let mut entries = Vec::new();
+        if source_map.is_empty() {
+            return Ok(SourceMap { entries });
+        }
+
+        let mut previous = SourceMapEntry {
+            start: -1,
+            length: -1,
+            source_index: -1,
+            jump: Jump::Regular,
+            modifier_depth: 0,
+        };
+        for (index, item) in source_map.split(';').enumerate() {
+            let mut entry = previous;
+            for (field, value) in item.split(':').enumerate() {
+                if value.is_empty() {
+                    continue;
+                }
+                let invalid = || {
+                    SolcError::SourceMapError(format!(
+                        "Invalid field {} in source map entry {}: {}",
+                        field, index, value
+                    ))
+                };
+                match field {
+                    0 => entry.start = value.parse().map_err(|_| invalid())?,
+                    1 => entry.length = value.parse().map_err(|_| invalid())?,
+                    2 => entry.source_index = value.parse().map_err(|_| invalid())?,
+                    3 => {
+                        entry.jump = match value {
+                            "i" => Jump::In,
+                            "o" => Jump::Out,
+                            "-" => Jump::Regular,
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    4 => entry.modifier_depth = value.parse().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                }
+            }
+            entries.push(entry);
+            previous = entry;
+        }
+        Ok(SourceMap { entries })
+    }
+
+    pub fn get(&self, instruction_index: usize) -> Option<&SourceMapEntry> {
+        self.entries.get(instruction_index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl FromStr for SourceMap {
+    type Err = SolcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SourceMap::parse(s)
+    }
+}
+
+/// The program counter of every instruction in `bytecode`, in order
+///
+/// `PUSH1`..`PUSH32` data is skipped, so `instruction_pcs(code)[i]` is the pc of the
+/// instruction described by source map entry `i`.
+pub fn instruction_pcs(bytecode: &[u8]) -> Vec<usize> {
+    disassemble(bytecode)
+        .into_iter()
+        .map(|instruction| instruction.pc)
+        .collect()
+}
+
+/// A source file and byte range an instruction was compiled from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcSourceLocation {
+    pub file: String,
+    pub start: u32,
+    pub length: u32,
+    pub jump: Jump,
+}
+
+/// Maps program counters of one bytecode object back to source locations
+#[derive(Debug, Clone)]
+pub struct PcSourceMapper {
+    instruction_pcs: Vec<usize>,
+    source_map: SourceMap,
+    files: HashMap<i64, String>,
+}
+
+impl PcSourceMapper {
+    /// `files` maps source ids to file names, see [`source_files_by_id`]
+    pub fn new(bytecode: &[u8], source_map: SourceMap, files: HashMap<i64, String>) -> Self {
+        PcSourceMapper {
+            instruction_pcs: instruction_pcs(bytecode),
+            source_map,
+            files,
+        }
+    }
+
+    /// Index of the instruction starting at `pc`
+    pub fn instruction_index(&self, pc: usize) -> Option<usize> {
+        self.instruction_pcs.binary_search(&pc).ok()
+    }
+
+    /// Source map entry of the instruction starting at `pc`
+    pub fn entry(&self, pc: usize) -> Option<&SourceMapEntry> {
+        self.source_map.get(self.instruction_index(pc)?)
+    }
+
+    /// File and byte range of the instruction starting at `pc`
+    ///
+    /// Returns `None` for compiler-internal instructions and for pcs that are not the start
+    /// of an instruction, such as push data or the metadata trailer.
+    pub fn location(&self, pc: usize) -> Option<PcSourceLocation> {
+        let entry = self.entry(pc)?;
+        if !entry.has_source() {
+            return None;
+        }
+        Some(PcSourceLocation {
+            file: self.files.get(&entry.source_index)?.clone(),
+            start: entry.start as u32,
+            length: entry.length as u32,
+            jump: entry.jump,
+        })
+    }
+}
+
+/// Source ids of `output.sources` plus the compiler-generated sources of one bytecode object
+pub fn source_files_by_id(
+    output: &SolcOutput,
+    generated_sources: Option<&Vec<SolcGeneratedSource>>,
+) -> HashMap<i64, String> {
+    let mut files: HashMap<i64, String> = output
+        .sources
+        .iter()
+        .flatten()
+        .map(|(file, source)| (source.id as i64, file.clone()))
+        .collect();
+    for generated in generated_sources.into_iter().flatten() {
+        files.insert(generated.id as i64, generated.name.clone());
+    }
+    files
+}
+
+impl SolcBytecodeOutput {
+    /// Decode `source_map` into one entry per instruction
+    pub fn decoded_source_map(&self) -> Result<Option<SourceMap>, SolcError> {
+        self.source_map.as_deref().map(SourceMap::parse).transpose()
+    }
+
+    /// Build a pc to source mapper using the `sources` id table of `output`
+    ///
+    /// Requires `object` and `sourceMap` to be selected.
+    pub fn pc_source_mapper(&self, output: &SolcOutput) -> Result<PcSourceMapper, SolcError> {
+        pc_source_mapper(
+            self.object.as_deref(),
+            self.source_map.as_deref(),
+            source_files_by_id(output, self.generated_sources.as_ref()),
+        )
+    }
+}
+
+impl SolcDeployedBytecodeOutput {
+    /// Decode `source_map` into one entry per instruction
+    pub fn decoded_source_map(&self) -> Result<Option<SourceMap>, SolcError> {
+        self.source_map.as_deref().map(SourceMap::parse).transpose()
+    }
+
+    /// Build a pc to source mapper using the `sources` id table of `output`
+    ///
+    /// Requires `object` and `sourceMap` to be selected.
+    pub fn pc_source_mapper(&self, output: &SolcOutput) -> Result<PcSourceMapper, SolcError> {
+        pc_source_mapper(
+            self.object.as_deref(),
+            self.source_map.as_deref(),
+            source_files_by_id(output, self.generated_sources.as_ref()),
+        )
+    }
+}
+
+fn pc_source_mapper(
+    object: Option<&str>,
+    source_map: Option<&str>,
+    files: HashMap<i64, String>,
+) -> Result<PcSourceMapper, SolcError> {
+    let object = object
+        .ok_or_else(|| SolcError::SourceMapError("Bytecode object was not selected".to_string()))?;
+    let source_map = source_map
+        .ok_or_else(|| SolcError::SourceMapError("Source map was not selected".to_string()))?;
+    let (bytecode, _) = decode_object(object)?;
+    Ok(PcSourceMapper::new(&bytecode, SourceMap::parse(source_map)?, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SolcSourceEntry;
+
+    #[test]
+    fn test_parse_compressed_source_map() {
+        // Example from the solidity docs
+        let expanded = SourceMap::parse("1:2:1;1:9:1;2:1:2;2:1:2;2:1:2").unwrap();
+        let compressed = SourceMap::parse("1:2:1;:9;2:1:2;;").unwrap();
+        assert_eq!(expanded, compressed);
+        assert_eq!(compressed.len(), 5);
+        assert_eq!(compressed.entries[1].length, 9);
+        assert_eq!(compressed.entries[1].source_index, 1);
+
+        let map = SourceMap::parse("0:10:0:-;5:3::i;::-1:o:1;:::-").unwrap();
+        assert_eq!(map.entries[1].jump, Jump::In);
+        assert_eq!(map.entries[1].source_index, 0);
+        assert_eq!(map.entries[2].source_index, -1);
+        assert_eq!(map.entries[2].jump, Jump::Out);
+        assert_eq!(map.entries[2].modifier_depth, 1);
+        assert!(!map.entries[2].has_source());
+        assert_eq!(map.entries[3].jump, Jump::Regular);
+        assert_eq!(map.entries[3].modifier_depth, 1);
+
+        assert!(matches!(
+            SourceMap::parse("0:1:0:x"),
+            Err(SolcError::SourceMapError(_))
+        ));
+    }
+
+    #[test]
+    fn test_maps_pc_to_file() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, PUSH2 0x0102, JUMP
+        let object = "608060405261010256";
+        let bytecode = SolcBytecodeOutput {
+            function_debug_data: None,
+            object: Some(object.to_string()),
+            opcodes: None,
+            source_map: Some("0:50:0;;10:5;20:7:1:i;::-1".to_string()),
+            generated_sources: None,
+            link_references: None,
+        };
+        let mut sources = HashMap::new();
+        sources.insert("A.sol".to_string(), SolcSourceEntry { id: 0, ast: None });
+        sources.insert("B.sol".to_string(), SolcSourceEntry { id: 1, ast: None });
+        let output = SolcOutput {
+            errors: None,
+            sources: Some(sources),
+            contracts: None,
+        };
+
+        let mapper = bytecode.pc_source_mapper(&output).unwrap();
+        assert_eq!(mapper.instruction_index(0), Some(0));
+        assert_eq!(mapper.instruction_index(4), Some(2));
+        assert_eq!(mapper.instruction_index(5), Some(3));
+        assert_eq!(mapper.instruction_index(8), Some(4));
+        // Push data is not an instruction
+        assert_eq!(mapper.instruction_index(1), None);
+
+        assert_eq!(
+            mapper.location(4),
+            Some(PcSourceLocation {
+                file: "A.sol".to_string(),
+                start: 10,
+                length: 5,
+                jump: Jump::Regular,
+            })
+        );
+        assert_eq!(mapper.location(5).unwrap().file, "B.sol");
+        assert_eq!(mapper.location(5).unwrap().jump, Jump::In);
+        assert_eq!(mapper.location(8), None);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::error::SolcError;
+use crate::models::{SolcStorageEncoding, SolcStorageLayout, SolcStorageLayoutItem, SolcStorageType};
+use alloy_primitives::{keccak256, B256, U256};
+
+/// Where a value lives in contract storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageSlot {
+    pub slot: U256,
+    /// Byte offset inside the slot, counted from the least significant byte
+    pub offset: u32,
+    /// Type id of the value, a key of `SolcStorageLayout::types`
+    pub type_id: String,
+    pub number_of_bytes: u32,
+}
+
+impl StorageSlot {
+    /// The slot as the 32-byte key used by `eth_getStorageAt` and storage overrides
+    pub fn key(&self) -> B256 {
+        B256::from(self.slot)
+    }
+}
+
+/// One step of a storage path
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment<'a> {
+    Member(&'a str),
+    Index(&'a str),
+}
+
+impl SolcStorageLayoutItem {
+    /// `slot` as a number; solc reports it as a decimal string
+    pub fn slot_number(&self) -> Result<U256, SolcError> {
+        U256::from_str_radix(&self.slot, 10).map_err(|_| {
+            SolcError::StorageLayoutError(format!("Invalid slot {} for {}", self.slot, self.label))
+        })
+    }
+}
+
+impl SolcStorageType {
+    pub fn size(&self) -> Result<u32, SolcError> {
+        self.number_of_bytes.parse().map_err(|_| {
+            SolcError::StorageLayoutError(format!(
+                "Invalid numberOfBytes {} for {}",
+                self.number_of_bytes, self.label
+            ))
+        })
+    }
+}
+
+impl SolcStorageLayout {
+    /// Type definition for a type id such as `t_mapping(t_address,t_uint256)`
+    pub fn storage_type(&self, type_id: &str) -> Result<&SolcStorageType, SolcError> {
+        self.types
+            .get(type_id)
+            .ok_or_else(|| SolcError::StorageLayoutError(format!("Unknown storage type {}", type_id)))
+    }
+
+    /// Compute the slot of a state variable path such as `owner`, `arr[3]`,
+    /// `balances[0xabc].amount` or `allowances[0x1][0x2]`
+    ///
+    /// Mapping keys are written the way the key type reads them: numbers in decimal or
+    /// `0x` hex, `true`/`false`, hex for `bytesN` and `bytes`, and raw text (optionally
+    /// quoted) for `string`.
+    pub fn slot(&self, path: &str) -> Result<StorageSlot, SolcError> {
+        let (root, segments) = parse_path(path)?;
+        let item = self
+            .storage
+            .iter()
+            .find(|item| item.label == root)
+            .ok_or_else(|| SolcError::StorageLayoutError(format!("No state variable named {}", root)))?;
+        let mut slot = StorageSlot {
+            slot: item.slot_number()?,
+            offset: item.offset,
+            type_id: item.type_name.clone(),
+            number_of_bytes: self.storage_type(&item.type_name)?.size()?,
+        };
+
+        for segment in segments {
+            let storage_type = self.storage_type(&slot.type_id)?;
+            slot = match segment {
+                PathSegment::Member(name) => {
+                    let member = storage_type
+                        .members
+                        .iter()
+                        .flatten()
+                        .find(|member| member.label == name)
+                        .ok_or_else(|| {
+                            SolcError::StorageLayoutError(format!(
+                                "{} has no member named {}",
+                                storage_type.label, name
+                            ))
+                        })?;
+                    StorageSlot {
+                        slot: slot.slot + member.slot_number()?,
+                        offset: member.offset,
+                        type_id: member.type_name.clone(),
+                        number_of_bytes: self.storage_type(&member.type_name)?.size()?,
+                    }
+                }
+                PathSegment::Index(key) => self.index(&slot, storage_type, key)?,
+            };
+        }
+        Ok(slot)
+    }
+
+    fn index(
+        &self,
+        parent: &StorageSlot,
+        storage_type: &SolcStorageType,
+        key: &str,
+    ) -> Result<StorageSlot, SolcError> {
+        match (storage_type.encoding, &storage_type.key, &storage_type.value, &storage_type.base) {
+            (SolcStorageEncoding::Mapping, Some(key_type), Some(value_type), _) => {
+                let mut preimage = encode_mapping_key(self.storage_type(key_type)?, key)?;
+                preimage.extend_from_slice(B256::from(parent.slot).as_slice());
+                Ok(StorageSlot {
+                    slot: keccak256(&preimage).into(),
+                    offset: 0,
+                    type_id: value_type.clone(),
+                    number_of_bytes: self.storage_type(value_type)?.size()?,
+                })
+            }
+            // Dynamic arrays store their length at the slot and their data at keccak(slot),
+            // static arrays store their data in place
+            (SolcStorageEncoding::DynamicArray | SolcStorageEncoding::Inplace, _, _, Some(base)) => {
+                let index = parse_uint(key)?;
+                if let Some(length) = static_array_length(&storage_type.label) {
+                    if index >= U256::from(length) {
+                        return Err(SolcError::StorageLayoutError(format!(
+                            "Index {} is out of bounds for {}",
+                            key, storage_type.label
+                        )));
+                    }
+                }
+                let data = match storage_type.encoding {
+                    SolcStorageEncoding::DynamicArray => {
+                        keccak256(B256::from(parent.slot)).into()
+                    }
+                    _ => parent.slot,
+                };
+                let size = self.storage_type(base)?.size()?;
+                let (slot, offset) = if size >= 32 {
+                    (data + index * U256::from(size.div_ceil(32)), 0)
+                } else {
+                    let per_slot = U256::from(32 / size);
+                    let offset: u32 = (index % per_slot).to::<u32>() * size;
+                    (data + index / per_slot, offset)
+                };
+                Ok(StorageSlot {
+                    slot,
+                    offset,
+                    type_id: base.clone(),
+                    number_of_bytes: size,
+                })
+            }
+            _ => Err(SolcError::StorageLayoutError(format!(
+                "{} cannot be indexed",
+                storage_type.label
+            ))),
+        }
+    }
+}
+
+fn parse_path(path: &str) -> Result<(&str, Vec<PathSegment<'_>>), SolcError> {
+    let invalid = || SolcError::StorageLayoutError(format!("Invalid storage path: {}", path));
+    let root_end = path.find(['.', '[']).unwrap_or(path.len());
+    let root = &path[..root_end];
+    if root.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut segments = Vec::new();
+    let mut rest = &path[root_end..];
+    while !rest.is_empty() {
+        if let Some(member) = rest.strip_prefix('.') {
+            let end = member.find(['.', '[']).unwrap_or(member.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(PathSegment::Member(&member[..end]));
+            rest = &member[end..];
+        } else if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']').ok_or_else(invalid)?;
+            segments.push(PathSegment::Index(index[..end].trim()));
+            rest = &index[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok((root, segments))
+}
+
+/// The bytes hashed together with the mapping slot for `key`
+fn encode_mapping_key(key_type: &SolcStorageType, key: &str) -> Result<Vec<u8>, SolcError> {
+    let label = key_type.label.as_str();
+    let invalid = || SolcError::StorageLayoutError(format!("Invalid {} mapping key: {}", label, key));
+    match label {
+        // Dynamic keys are hashed unpadded
+        "string" => Ok(key.trim_matches('"').as_bytes().to_vec()),
+        "bytes" => decode_hex(key).ok_or_else(invalid),
+        "bool" => match key {
+            "true" => Ok(U256::from(1).to_be_bytes_vec()),
+            "false" => Ok(U256::ZERO.to_be_bytes_vec()),
+            _ => Err(invalid()),
+        },
+        _ if label.starts_with("bytes") => {
+            let mut bytes = decode_hex(key).ok_or_else(invalid)?;
+            if bytes.len() > 32 {
+                return Err(invalid());
+            }
+            bytes.resize(32, 0);
+            Ok(bytes)
+        }
+        _ if label.starts_with("int") => {
+            let value = match key.strip_prefix('-') {
+                Some(magnitude) => parse_uint(magnitude).map_err(|_| invalid())?.wrapping_neg(),
+                None => parse_uint(key).map_err(|_| invalid())?,
+            };
+            Ok(value.to_be_bytes_vec())
+        }
+        // uintN, address, address payable, enums and contracts are left padded numbers
+        _ => Ok(parse_uint(key).map_err(|_| invalid())?.to_be_bytes_vec()),
+    }
+}
+
+fn parse_uint(value: &str) -> Result<U256, SolcError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16),
+        None => U256::from_str_radix(value, 10),
+    };
+    parsed.map_err(|_| SolcError::StorageLayoutError(format!("Invalid number: {}", value)))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value)).ok()
+}
+
+/// `3` for a label such as `uint256[3]`, `None` for dynamic arrays
+fn static_array_length(label: &str) -> Option<u64> {
+    let inner = label.strip_suffix(']')?;
+    inner[inner.rfind('[')? + 1..].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn layout() -> SolcStorageLayout {
+        serde_json::from_value(json!({
+            "storage": [
+                { "astId": 1, "contract": "V.sol:V", "label": "owner", "offset": 0, "slot": "0", "type": "t_address" },
+                { "astId": 2, "contract": "V.sol:V", "label": "paused", "offset": 20, "slot": "0", "type": "t_bool" },
+                { "astId": 3, "contract": "V.sol:V", "label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_struct(Account)10_storage)" },
+                { "astId": 4, "contract": "V.sol:V", "label": "arr", "offset": 0, "slot": "2", "type": "t_array(t_uint256)dyn_storage" },
+                { "astId": 5, "contract": "V.sol:V", "label": "small", "offset": 0, "slot": "3", "type": "t_array(t_uint64)4_storage" },
+                { "astId": 6, "contract": "V.sol:V", "label": "names", "offset": 0, "slot": "4", "type": "t_mapping(t_string_memory_ptr,t_uint256)" }
+            ],
+            "types": {
+                "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+                "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+                "t_uint64": { "encoding": "inplace", "label": "uint64", "numberOfBytes": "8" },
+                "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+                "t_string_memory_ptr": { "encoding": "bytes", "label": "string", "numberOfBytes": "32" },
+                "t_array(t_uint256)dyn_storage": { "encoding": "dynamic_array", "label": "uint256[]", "numberOfBytes": "32", "base": "t_uint256" },
+                "t_array(t_uint64)4_storage": { "encoding": "inplace", "label": "uint64[4]", "numberOfBytes": "32", "base": "t_uint64" },
+                "t_mapping(t_address,t_struct(Account)10_storage)": {
+                    "encoding": "mapping", "label": "mapping(address => struct V.Account)", "numberOfBytes": "32",
+                    "key": "t_address", "value": "t_struct(Account)10_storage"
+                },
+                "t_mapping(t_string_memory_ptr,t_uint256)": {
+                    "encoding": "mapping", "label": "mapping(string => uint256)", "numberOfBytes": "32",
+                    "key": "t_string_memory_ptr", "value": "t_uint256"
+                },
+                "t_struct(Account)10_storage": {
+                    "encoding": "inplace", "label": "struct V.Account", "numberOfBytes": "64",
+                    "members": [
+                        { "astId": 7, "contract": "V.sol:V", "label": "nonce", "offset": 0, "slot": "0", "type": "t_uint256" },
+                        { "astId": 8, "contract": "V.sol:V", "label": "amount", "offset": 0, "slot": "1", "type": "t_uint256" }
+                    ]
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn hash(words: &[U256]) -> U256 {
+        let preimage: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes::<32>()).collect();
+        keccak256(preimage).into()
+    }
+
+    #[test]
+    fn test_mapping_and_struct_slots() {
+        let layout = layout();
+        let paused = layout.slot("paused").unwrap();
+        assert_eq!((paused.slot, paused.offset, paused.number_of_bytes), (U256::ZERO, 20, 1));
+
+        let amount = layout.slot("balances[0xabc].amount").unwrap();
+        assert_eq!(amount.slot, hash(&[U256::from(0xabc), U256::from(1)]) + U256::from(1));
+        assert_eq!(amount.type_id, "t_uint256");
+
+        let mut preimage = b"alice".to_vec();
+        preimage.extend_from_slice(&U256::from(4).to_be_bytes::<32>());
+        assert_eq!(
+            layout.slot(r#"names["alice"]"#).unwrap().key(),
+            keccak256(preimage)
+        );
+    }
+
+    #[test]
+    fn test_array_slots() {
+        let layout = layout();
+        assert_eq!(
+            layout.slot("arr[3]").unwrap().slot,
+            hash(&[U256::from(2)]) + U256::from(3)
+        );
+
+        // Four uint64s share one slot
+        let third = layout.slot("small[2]").unwrap();
+        assert_eq!((third.slot, third.offset), (U256::from(3), 16));
+        assert!(matches!(
+            layout.slot("small[4]"),
+            Err(SolcError::StorageLayoutError(_))
+        ));
+        assert!(matches!(
+            layout.slot("owner[1]"),
+            Err(SolcError::StorageLayoutError(_))
+        ));
+        assert!(matches!(layout.slot("arr[1"), Err(SolcError::StorageLayoutError(_))));
+    }
+}
@@ -0,0 +1,500 @@
+use crate::error::SolcError;
+use crate::solc::Solc;
+use alloy_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Environment variable that overrides the default store location
+pub const SOLC_STORE_ENV: &str = "TEVM_SOLC_HOME";
+
+/// Name of the checksum list kept at the root of the store
+const BUILD_LIST_FILE: &str = "list.json";
+
+#[cfg(windows)]
+const SOLC_BINARY_NAME: &str = "solc.exe";
+#[cfg(not(windows))]
+const SOLC_BINARY_NAME: &str = "solc";
+
+/// A single solc build, in the format used by binaries.soliditylang.org `list.json`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolcBuildInfo {
+    /// File name of the binary relative to the list it came from
+    pub path: String,
+    /// Bare version, e.g. `0.8.20`
+    pub version: String,
+    /// Version including the commit, e.g. `0.8.20+commit.a1b79de6`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_version: Option<String>,
+    /// `0x` prefixed keccak256 of the binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keccak256: Option<String>,
+    /// `0x` prefixed sha256 of the binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// List of known solc builds and their checksums
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SolcBuildList {
+    pub builds: Vec<SolcBuildInfo>,
+}
+
+impl SolcBuildList {
+    /// Find the build entry for a bare version such as `0.8.20`
+    pub fn find(&self, version: &str) -> Option<&SolcBuildInfo> {
+        self.builds.iter().find(|build| build.version == version)
+    }
+
+    /// Insert a build, replacing any existing entry for the same version
+    pub fn upsert(&mut self, build: SolcBuildInfo) {
+        self.builds.retain(|existing| existing.version != build.version);
+        self.builds.push(build);
+    }
+}
+
+/// Provides solc binaries to a [`SolcStore`]
+///
+/// A binary is only moved into the store once it matches the checksums returned by
+/// [`SolcInstaller::build_list`]. Those checksums are the installer's own, so they catch
+/// corrupted or truncated downloads, not an installer that lies about its builds.
+pub trait SolcInstaller: Send + Sync {
+    /// The builds this installer can provide, with their checksums
+    fn build_list(&self) -> Result<SolcBuildList, SolcError>;
+
+    /// Write the binary for `build` to `dest`
+    fn install(&self, build: &SolcBuildInfo, dest: &Path) -> Result<(), SolcError>;
+}
+
+/// Installs solc binaries from a local directory laid out like binaries.soliditylang.org
+///
+/// The directory must contain a `list.json` and the binaries it references by `path`.
+/// Mostly useful for tests and air-gapped CI.
+#[derive(Debug, Clone)]
+pub struct LocalDirInstaller {
+    pub dir: PathBuf,
+}
+
+impl LocalDirInstaller {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LocalDirInstaller { dir: dir.into() }
+    }
+}
+
+impl SolcInstaller for LocalDirInstaller {
+    fn build_list(&self) -> Result<SolcBuildList, SolcError> {
+        read_build_list(&self.dir.join(BUILD_LIST_FILE))
+    }
+
+    fn install(&self, build: &SolcBuildInfo, dest: &Path) -> Result<(), SolcError> {
+        fs::copy(self.dir.join(&build.path), dest)?;
+        Ok(())
+    }
+}
+
+/// A versioned on-disk store of solc binaries
+///
+/// Binaries live at `<root>/<version>/solc` and are verified against the checksums in
+/// `<root>/list.json`, recorded when they were installed, every time they are looked up.
+pub struct SolcStore {
+    root: PathBuf,
+    installer: Option<Box<dyn SolcInstaller>>,
+}
+
+impl SolcStore {
+    /// Create a store rooted at `root` without an installer
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        SolcStore {
+            root: root.into(),
+            installer: None,
+        }
+    }
+
+    /// The default store, `$TEVM_SOLC_HOME` or `~/.tevm/solc`
+    pub fn default_store() -> Result<Self, SolcError> {
+        Ok(SolcStore::new(default_root()?))
+    }
+
+    /// Use `installer` to fetch versions that are not in the store yet
+    pub fn with_installer(mut self, installer: impl SolcInstaller + 'static) -> Self {
+        self.installer = Some(Box::new(installer));
+        self
+    }
+
+    /// The root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path a given version is stored at, whether or not it is installed
+    ///
+    /// `version` must be a semver version such as `0.8.20` or `v0.8.20`; build metadata and
+    /// pre-release tags are dropped.
+    pub fn binary_path(&self, version: &str) -> Result<PathBuf, SolcError> {
+        Ok(self.root.join(normalize_version(version)?).join(SOLC_BINARY_NAME))
+    }
+
+    /// Versions currently present in the store
+    pub fn installed_versions(&self) -> Result<Vec<String>, SolcError> {
+        if !self.root.exists() {
+            return Ok(vec![]);
+        }
+        let mut versions = vec![];
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let Ok(version) = Version::parse(&entry.file_name().to_string_lossy()) else {
+                continue;
+            };
+            if entry.path().join(SOLC_BINARY_NAME).is_file() {
+                versions.push(version);
+            }
+        }
+        versions.sort();
+        Ok(versions.iter().map(Version::to_string).collect())
+    }
+
+    /// Get a verified [`Solc`] for `version`, installing it first if an installer is set
+    pub fn get(&self, version: &str) -> Result<Solc, SolcError> {
+        let version = normalize_version(version)?;
+        let path = self.binary_path(&version)?;
+        if path.is_file() {
+            return self.verify(&version);
+        }
+        match &self.installer {
+            Some(installer) => {
+                self.install(installer.as_ref(), &version)?;
+                Ok(Solc::new(path, version))
+            }
+            None => Err(SolcError::VersionNotInstalled { version, path }),
+        }
+    }
+
+    /// Install `version` using `installer` and record its checksums in the store
+    ///
+    /// The binary is written next to its final path and only renamed into place once it
+    /// passes verification; a binary that fails is deleted.
+    pub fn install(&self, installer: &dyn SolcInstaller, version: &str) -> Result<(), SolcError> {
+        let version = normalize_version(version)?;
+        let path = self.binary_path(&version)?;
+        let build = installer
+            .build_list()?
+            .find(&version)
+            .cloned()
+            .ok_or_else(|| SolcError::VersionNotInstalled {
+                version: version.clone(),
+                path: path.clone(),
+            })?;
+
+        let dir = path.parent().expect("binary path has a version directory");
+        fs::create_dir_all(dir)?;
+        // Closed before running it; an executable open for writing cannot be spawned
+        let temp = tempfile::NamedTempFile::new_in(dir)?.into_temp_path();
+        installer.install(&build, &temp)?;
+        make_executable(&temp)?;
+        check_binary(&temp, &version, &build)?;
+        temp.persist(&path).map_err(|e| SolcError::TempFileError(e.error))?;
+
+        let list_path = self.root.join(BUILD_LIST_FILE);
+        let mut list = if list_path.exists() {
+            read_build_list(&list_path)?
+        } else {
+            SolcBuildList::default()
+        };
+        list.upsert(build);
+        let json = serde_json::to_string_pretty(&list)
+            .map_err(|e| SolcError::SerializationError(e.to_string()))?;
+        fs::write(list_path, json)?;
+        Ok(())
+    }
+
+    /// Check an installed binary against the checksum list and its `--version` output
+    fn verify(&self, version: &str) -> Result<Solc, SolcError> {
+        let path = self.binary_path(version)?;
+        let list_path = self.root.join(BUILD_LIST_FILE);
+        let list = if list_path.exists() {
+            read_build_list(&list_path)?
+        } else {
+            SolcBuildList::default()
+        };
+        let build = list
+            .find(version)
+            .ok_or_else(|| SolcError::MissingChecksum(version.to_string()))?;
+        check_binary(&path, version, build)?;
+        Ok(Solc::new(path, version.to_string()))
+    }
+}
+
+/// Check the binary at `path` against the checksums of `build` and its `--version` output
+fn check_binary(path: &Path, version: &str, build: &SolcBuildInfo) -> Result<(), SolcError> {
+    let bytes = fs::read(path)?;
+    let mut checked = false;
+    if let Some(expected) = &build.keccak256 {
+        let actual = format!("0x{}", hex::encode(keccak256(&bytes)));
+        check_digest(version, expected, actual)?;
+        checked = true;
+    }
+    if let Some(expected) = &build.sha256 {
+        let actual = format!("0x{}", hex::encode(Sha256::digest(&bytes)));
+        check_digest(version, expected, actual)?;
+        checked = true;
+    }
+    if !checked {
+        return Err(SolcError::MissingChecksum(version.to_string()));
+    }
+
+    let reported = solc_version(path)?;
+    if reported != version {
+        return Err(SolcError::VersionMismatch {
+            expected: version.to_string(),
+            actual: reported,
+        });
+    }
+    Ok(())
+}
+
+/// Create a [`Solc`] for `version` from the default store
+///
+/// The binary must already be installed at `~/.tevm/solc/<version>/solc` (or under
+/// `$TEVM_SOLC_HOME`) and match the checksums in the store's `list.json`.
+/// Use [`SolcStore::with_installer`] to install missing versions. The lookup reads the
+/// binary and runs `solc --version`, so it happens on the blocking thread pool.
+pub async fn create_solc(version: &str) -> Result<Solc, SolcError> {
+    let version = version.to_string();
+    tokio::task::spawn_blocking(move || SolcStore::default_store()?.get(&version))
+        .await
+        .map_err(|e| SolcError::Unknown(format!("Failed to look up solc: {}", e)))?
+}
+
+/// Run `solc --version` and return the bare version, e.g. `0.8.20`
+pub fn solc_version(path: &Path) -> Result<String, SolcError> {
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .map_err(|e| SolcError::CompilationError(format!("Failed to execute solc: {}", e)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Version:"))
+        .map(|version| {
+            let version = version.trim();
+            version
+                .split(['+', '-'])
+                .next()
+                .unwrap_or(version)
+                .to_string()
+        })
+        .ok_or_else(|| {
+            SolcError::Unknown(format!("Unexpected solc --version output: {}", stdout.trim()))
+        })
+}
+
+fn default_root() -> Result<PathBuf, SolcError> {
+    if let Ok(root) = std::env::var(SOLC_STORE_ENV) {
+        return Ok(PathBuf::from(root));
+    }
+    dirs::home_dir()
+        .map(|home| home.join(".tevm").join("solc"))
+        .ok_or_else(|| SolcError::Unknown("Could not determine home directory".to_string()))
+}
+
+/// The bare `major.minor.patch` of `version`, which names its directory in the store
+fn normalize_version(version: &str) -> Result<String, SolcError> {
+    let version = version.trim();
+    let parsed = Version::parse(version.strip_prefix('v').unwrap_or(version))
+        .map_err(|e| SolcError::InvalidVersion(format!("{}: {}", version, e)))?;
+    Ok(Version::new(parsed.major, parsed.minor, parsed.patch).to_string())
+}
+
+fn read_build_list(path: &Path) -> Result<SolcBuildList, SolcError> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json)
+        .map_err(|e| SolcError::SerializationError(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+fn check_digest(version: &str, expected: &str, actual: String) -> Result<(), SolcError> {
+    let normalize = |digest: &str| digest.trim_start_matches("0x").to_lowercase();
+    if normalize(expected) != normalize(&actual) {
+        return Err(SolcError::ChecksumMismatch {
+            version: version.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), SolcError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), SolcError> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Writes a fake solc that only answers `--version` and a matching list.json
+    fn seed_release_dir(dir: &Path, version: &str) -> SolcBuildInfo {
+        let script = format!(
+            "#!/bin/sh\necho 'solc, the solidity compiler commandline interface'\necho 'Version: {}+commit.deadbeef.Linux.g++'\n",
+            version
+        );
+        let file_name = format!("solc-v{}", version);
+        fs::write(dir.join(&file_name), &script).unwrap();
+        let build = SolcBuildInfo {
+            path: file_name,
+            version: version.to_string(),
+            long_version: Some(format!("{}+commit.deadbeef", version)),
+            keccak256: Some(format!("0x{}", hex::encode(keccak256(script.as_bytes())))),
+            sha256: Some(format!("0x{}", hex::encode(Sha256::digest(script.as_bytes())))),
+        };
+        let list = SolcBuildList {
+            builds: vec![build.clone()],
+        };
+        fs::write(dir.join(BUILD_LIST_FILE), serde_json::to_string(&list).unwrap()).unwrap();
+        build
+    }
+
+    #[test]
+    fn test_missing_version_without_installer() {
+        let store_dir = tempdir().unwrap();
+        let store = SolcStore::new(store_dir.path());
+        match store.get("0.8.20") {
+            Err(SolcError::VersionNotInstalled { version, path }) => {
+                assert_eq!(version, "0.8.20");
+                assert_eq!(path, store_dir.path().join("0.8.20").join("solc"));
+            }
+            other => panic!("Expected VersionNotInstalled, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_installs_and_verifies_from_local_dir() {
+        let release_dir = tempdir().unwrap();
+        let store_dir = tempdir().unwrap();
+        seed_release_dir(release_dir.path(), "0.8.20");
+
+        let store =
+            SolcStore::new(store_dir.path()).with_installer(LocalDirInstaller::new(release_dir.path()));
+        let solc = store.get("v0.8.20").unwrap();
+        assert_eq!(solc.version, "0.8.20");
+        assert_eq!(*solc.path, store_dir.path().join("0.8.20").join("solc"));
+        assert_eq!(store.installed_versions().unwrap(), vec!["0.8.20".to_string()]);
+
+        // A second lookup uses the installed copy without the installer
+        let solc = SolcStore::new(store_dir.path()).get("0.8.20").unwrap();
+        assert_eq!(solc.version, "0.8.20");
+    }
+
+    #[test]
+    fn test_rejects_tampered_binary() {
+        let release_dir = tempdir().unwrap();
+        let store_dir = tempdir().unwrap();
+        seed_release_dir(release_dir.path(), "0.8.20");
+
+        let store =
+            SolcStore::new(store_dir.path()).with_installer(LocalDirInstaller::new(release_dir.path()));
+        store.get("0.8.20").unwrap();
+
+        fs::write(store.binary_path("0.8.20").unwrap(), "#!/bin/sh\necho 'Version: 0.8.20'\n").unwrap();
+        assert!(matches!(
+            store.get("0.8.20"),
+            Err(SolcError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_wrong_reported_version() {
+        let release_dir = tempdir().unwrap();
+        let store_dir = tempdir().unwrap();
+        let mut build = seed_release_dir(release_dir.path(), "0.8.19");
+        // Advertise the 0.8.19 binary as 0.8.20
+        build.version = "0.8.20".to_string();
+        let list = SolcBuildList {
+            builds: vec![build],
+        };
+        fs::write(
+            release_dir.path().join(BUILD_LIST_FILE),
+            serde_json::to_string(&list).unwrap(),
+        )
+        .unwrap();
+
+        let store =
+            SolcStore::new(store_dir.path()).with_installer(LocalDirInstaller::new(release_dir.path()));
+        match store.get("0.8.20") {
+            Err(SolcError::VersionMismatch { expected, actual }) => {
+                assert_eq!(expected, "0.8.20");
+                assert_eq!(actual, "0.8.19");
+            }
+            other => panic!("Expected VersionMismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deletes_binary_that_fails_verification() {
+        let release_dir = tempdir().unwrap();
+        let store_dir = tempdir().unwrap();
+        let mut build = seed_release_dir(release_dir.path(), "0.8.20");
+        build.sha256 = Some(format!("0x{}", "00".repeat(32)));
+        let list = SolcBuildList {
+            builds: vec![build],
+        };
+        fs::write(
+            release_dir.path().join(BUILD_LIST_FILE),
+            serde_json::to_string(&list).unwrap(),
+        )
+        .unwrap();
+
+        let store =
+            SolcStore::new(store_dir.path()).with_installer(LocalDirInstaller::new(release_dir.path()));
+        assert!(matches!(
+            store.get("0.8.20"),
+            Err(SolcError::ChecksumMismatch { .. })
+        ));
+        let version_dir = store_dir.path().join("0.8.20");
+        assert_eq!(fs::read_dir(version_dir).unwrap().count(), 0);
+        assert!(!store_dir.path().join(BUILD_LIST_FILE).exists());
+    }
+
+    #[test]
+    fn test_rejects_versions_that_are_not_semver() {
+        let store = SolcStore::new("/store");
+        assert_eq!(
+            store.binary_path("v0.8.20+commit.a1b79de6").unwrap(),
+            Path::new("/store/0.8.20/solc")
+        );
+        for version in ["../../x", "0.8", "latest"] {
+            assert!(matches!(
+                store.get(version),
+                Err(SolcError::InvalidVersion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sorts_installed_versions_numerically() {
+        let store_dir = tempdir().unwrap();
+        for version in ["0.8.10", "0.8.9", "0.7.6", "not-a-version"] {
+            let dir = store_dir.path().join(version);
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join(SOLC_BINARY_NAME), "").unwrap();
+        }
+        assert_eq!(
+            SolcStore::new(store_dir.path()).installed_versions().unwrap(),
+            vec!["0.7.6", "0.8.9", "0.8.10"]
+        );
+    }
+}
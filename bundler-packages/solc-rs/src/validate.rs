@@ -0,0 +1,234 @@
+use crate::models::SolcSettings;
+use semver::Version;
+use std::fmt;
+
+/// A setting the given solc version would reject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsIssue {
+    /// JSON path of the offending field, e.g. `settings.viaIR`
+    pub field: String,
+    pub message: String,
+}
+
+impl SettingsIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        SettingsIssue {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SettingsIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+const REVERT_STRINGS: [&str; 4] = ["default", "strip", "debug", "verboseDebug"];
+const BYTECODE_HASHES: [&str; 3] = ["ipfs", "bzzr1", "none"];
+
+/// Check `settings` against what solc `version` accepts, without spawning it
+///
+/// Covers fields newer than the compiler (`viaIR` before 0.8.13, `debug.debugInfo` before
+/// 0.8.7, `metadata.appendCBOR` before 0.8.18, `metadata.bytecodeHash` before 0.6.0),
+/// unknown values of enum-like string fields, and outputs selected past `stopAfter`.
+/// `evmVersion` is checked separately by [`crate::EvmVersion::check`].
+pub fn validate(settings: &SolcSettings, version: &Version) -> Vec<SettingsIssue> {
+    let version = Version::new(version.major, version.minor, version.patch);
+    let mut issues = vec![];
+    let mut requires = |field: &str, set: bool, (major, minor, patch): (u64, u64, u64)| {
+        let min = Version::new(major, minor, patch);
+        if set && version < min {
+            issues.push(SettingsIssue::new(
+                field,
+                format!("requires solc >= {}, but solc {} is used", min, version),
+            ));
+        }
+    };
+
+    requires("settings.viaIR", settings.via_ir == Some(true), (0, 8, 13));
+    let debug = settings.debug.as_ref();
+    requires(
+        "settings.debug.debugInfo",
+        debug.is_some_and(|debug| debug.debug_info.is_some()),
+        (0, 8, 7),
+    );
+    let metadata = settings.metadata.as_ref();
+    requires(
+        "settings.metadata.appendCBOR",
+        metadata.is_some_and(|metadata| metadata.append_cbor.is_some()),
+        (0, 8, 18),
+    );
+    requires(
+        "settings.metadata.bytecodeHash",
+        metadata.is_some_and(|metadata| metadata.bytecode_hash.is_some()),
+        (0, 6, 0),
+    );
+
+    if let Some(revert_strings) = debug.and_then(|debug| debug.revert_strings.as_deref()) {
+        if !REVERT_STRINGS.contains(&revert_strings) {
+            issues.push(SettingsIssue::new(
+                "settings.debug.revertStrings",
+                format!(
+                    "unknown value \"{}\", expected one of {}",
+                    revert_strings,
+                    REVERT_STRINGS.join(", ")
+                ),
+            ));
+        }
+    }
+    if let Some(bytecode_hash) = metadata.and_then(|metadata| metadata.bytecode_hash.as_deref()) {
+        if !BYTECODE_HASHES.contains(&bytecode_hash) {
+            issues.push(SettingsIssue::new(
+                "settings.metadata.bytecodeHash",
+                format!(
+                    "unknown value \"{}\", expected one of {}",
+                    bytecode_hash,
+                    BYTECODE_HASHES.join(", ")
+                ),
+            ));
+        }
+    }
+    if metadata.is_some_and(|metadata| {
+        metadata.append_cbor == Some(false)
+            && metadata.bytecode_hash.as_deref().is_some_and(|hash| hash != "none")
+    }) {
+        issues.push(SettingsIssue::new(
+            "settings.metadata.bytecodeHash",
+            "must be \"none\" when appendCBOR is false",
+        ));
+    }
+
+    if let Some(stop_after) = settings.stop_after.as_deref() {
+        if stop_after != "parsing" {
+            issues.push(SettingsIssue::new(
+                "settings.stopAfter",
+                format!("unknown value \"{}\", expected parsing", stop_after),
+            ));
+        }
+        // Parsing produces nothing but the AST
+        let mut selections: Vec<_> = settings.output_selection.iter().flatten().collect();
+        selections.sort_by_key(|(file, _)| *file);
+        for (file, contracts) in selections {
+            let mut contracts: Vec<_> = contracts.iter().collect();
+            contracts.sort_by_key(|(contract, _)| *contract);
+            for (contract, outputs) in contracts {
+                if let Some(output) = outputs.iter().find(|output| *output != "ast") {
+                    issues.push(SettingsIssue::new(
+                        format!("settings.outputSelection.{}.{}", file, contract),
+                        format!("cannot select \"{}\" with stopAfter, only \"ast\"", output),
+                    ));
+                }
+            }
+        }
+    }
+    issues
+}
+
+impl SolcSettings {
+    /// See [`validate`]
+    pub fn validate(&self, version: &Version) -> Vec<SettingsIssue> {
+        validate(self, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{OutputSelectionBuilder, OutputSelectionItem};
+    use crate::models::{SolcDebugSettings, SolcMetadataSettings};
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_flags_fields_newer_than_the_compiler() {
+        let settings = SolcSettings {
+            via_ir: Some(true),
+            debug: Some(SolcDebugSettings {
+                revert_strings: Some("strip".to_string()),
+                debug_info: Some(vec!["location".to_string()]),
+            }),
+            metadata: Some(SolcMetadataSettings {
+                append_cbor: Some(false),
+                use_literal_content: None,
+                bytecode_hash: Some("none".to_string()),
+            }),
+            ..Default::default()
+        };
+        assert!(settings.validate(&v("0.8.20")).is_empty());
+
+        let fields: Vec<String> = validate(&settings, &v("0.8.10"))
+            .into_iter()
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(fields, vec!["settings.viaIR", "settings.metadata.appendCBOR"]);
+
+        let issues = validate(&settings, &v("0.5.17"));
+        assert_eq!(issues.len(), 4);
+        assert_eq!(
+            issues[1].to_string(),
+            "settings.debug.debugInfo: requires solc >= 0.8.7, but solc 0.5.17 is used"
+        );
+    }
+
+    #[test]
+    fn test_flags_invalid_values() {
+        let settings = SolcSettings {
+            stop_after: Some("parsing".to_string()),
+            output_selection: Some(
+                OutputSelectionBuilder::new()
+                    .file("A.sol", [OutputSelectionItem::Ast])
+                    .contract("A.sol", "A", [OutputSelectionItem::Abi])
+                    .build(),
+            ),
+            debug: Some(SolcDebugSettings {
+                revert_strings: Some("verbose".to_string()),
+                debug_info: None,
+            }),
+            metadata: Some(SolcMetadataSettings {
+                append_cbor: Some(false),
+                use_literal_content: None,
+                bytecode_hash: Some("ipfs".to_string()),
+            }),
+            ..Default::default()
+        };
+        let issues: Vec<String> = validate(&settings, &v("0.8.24"))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "settings.debug.revertStrings: unknown value \"verbose\", expected one of default, strip, debug, verboseDebug",
+                "settings.metadata.bytecodeHash: must be \"none\" when appendCBOR is false",
+                "settings.outputSelection.A.sol.A: cannot select \"abi\" with stopAfter, only \"ast\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checks_append_cbor_as_solc_spells_it() {
+        let settings: SolcSettings = serde_json::from_str(
+            r#"{"metadata":{"appendCBOR":false,"bytecodeHash":"ipfs"}}"#,
+        )
+        .unwrap();
+        let issues: Vec<String> = validate(&settings, &v("0.8.17"))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "settings.metadata.appendCBOR: requires solc >= 0.8.18, but solc 0.8.17 is used",
+                "settings.metadata.bytecodeHash: must be \"none\" when appendCBOR is false",
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap()["metadata"]["appendCBOR"],
+            false
+        );
+    }
+}